@@ -0,0 +1,395 @@
+// Quake 3 player animation.cfg parser.
+//
+// The file starts with optional keywords (sex, footsteps, headoffset,
+// fixedlegs, fixedtorso) followed by one line per animation:
+//
+//     first_frame  num_frames  looping_frames  fps
+//
+// in the fixed order of `AnimationId`.
+
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use math::Vec3;
+use script::Tokenizer;
+
+/// Player animations in the order they appear in animation.cfg.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum AnimationId
+{
+    BothDeath1,
+    BothDead1,
+    BothDeath2,
+    BothDead2,
+    BothDeath3,
+    BothDead3,
+
+    TorsoGesture,
+    TorsoAttack,
+    TorsoAttack2,
+    TorsoDrop,
+    TorsoRaise,
+    TorsoStand,
+    TorsoStand2,
+
+    LegsWalkCr,
+    LegsWalk,
+    LegsRun,
+    LegsBack,
+    LegsSwim,
+    LegsJump,
+    LegsLand,
+    LegsJumpB,
+    LegsLandB,
+    LegsIdle,
+    LegsIdleCr,
+    LegsTurn,
+
+    // Team Arena additions, optional in the file
+    TorsoGetFlag,
+    TorsoGuardBase,
+    TorsoPatrol,
+    TorsoFollowMe,
+    TorsoAffirmative,
+    TorsoNegative,
+}
+
+use self::AnimationId::*;
+
+pub const MAX_ANIMATIONS : usize = 31;
+
+const ALL_ANIMATIONS : [AnimationId; MAX_ANIMATIONS] = [
+    BothDeath1, BothDead1, BothDeath2, BothDead2, BothDeath3, BothDead3,
+    TorsoGesture, TorsoAttack, TorsoAttack2, TorsoDrop, TorsoRaise, TorsoStand, TorsoStand2,
+    LegsWalkCr, LegsWalk, LegsRun, LegsBack, LegsSwim, LegsJump, LegsLand,
+    LegsJumpB, LegsLandB, LegsIdle, LegsIdleCr, LegsTurn,
+    TorsoGetFlag, TorsoGuardBase, TorsoPatrol, TorsoFollowMe, TorsoAffirmative, TorsoNegative,
+];
+
+impl AnimationId
+{
+    pub fn all() -> &'static [AnimationId]
+    {
+        &ALL_ANIMATIONS
+    }
+
+    /// Name used by the engine, e.g. "LEGS_RUN".
+    pub fn name( self ) -> &'static str
+    {
+        match self {
+            BothDeath1 => "BOTH_DEATH1",
+            BothDead1 => "BOTH_DEAD1",
+            BothDeath2 => "BOTH_DEATH2",
+            BothDead2 => "BOTH_DEAD2",
+            BothDeath3 => "BOTH_DEATH3",
+            BothDead3 => "BOTH_DEAD3",
+            TorsoGesture => "TORSO_GESTURE",
+            TorsoAttack => "TORSO_ATTACK",
+            TorsoAttack2 => "TORSO_ATTACK2",
+            TorsoDrop => "TORSO_DROP",
+            TorsoRaise => "TORSO_RAISE",
+            TorsoStand => "TORSO_STAND",
+            TorsoStand2 => "TORSO_STAND2",
+            LegsWalkCr => "LEGS_WALKCR",
+            LegsWalk => "LEGS_WALK",
+            LegsRun => "LEGS_RUN",
+            LegsBack => "LEGS_BACK",
+            LegsSwim => "LEGS_SWIM",
+            LegsJump => "LEGS_JUMP",
+            LegsLand => "LEGS_LAND",
+            LegsJumpB => "LEGS_JUMPB",
+            LegsLandB => "LEGS_LANDB",
+            LegsIdle => "LEGS_IDLE",
+            LegsIdleCr => "LEGS_IDLECR",
+            LegsTurn => "LEGS_TURN",
+            TorsoGetFlag => "TORSO_GETFLAG",
+            TorsoGuardBase => "TORSO_GUARDBASE",
+            TorsoPatrol => "TORSO_PATROL",
+            TorsoFollowMe => "TORSO_FOLLOWME",
+            TorsoAffirmative => "TORSO_AFFIRMATIVE",
+            TorsoNegative => "TORSO_NEGATIVE",
+        }
+    }
+
+    /// True for animations played on lower.md3, whose frame numbers are
+    /// rebased when the file is parsed.
+    pub fn is_legs( self ) -> bool
+    {
+        let i = self as usize;
+        i >= LegsWalkCr as usize && i <= LegsTurn as usize
+    }
+
+    /// True for animations played on upper.md3.
+    pub fn is_torso( self ) -> bool
+    {
+        !self.is_legs() && ( self as usize ) >= TorsoGesture as usize
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct AnimationClip
+{
+    /// First frame within the md3 the clip plays on
+    pub first_frame : i32,
+    pub num_frames : i32,
+    /// Number of trailing frames repeated once the clip has played through, 0 to hold the last frame
+    pub looping_frames : i32,
+    pub fps : f32,
+    /// A negative frame count in the file plays the clip backwards
+    pub reversed : bool,
+}
+
+impl AnimationClip
+{
+    /// Maps the `n`th frame since the clip started to a model frame, applying
+    /// looping and reversal like CG_RunLerpFrame.
+    pub fn frame_index( &self, n : u32 ) -> u32
+    {
+        let num = self.num_frames.max( 1 ) as u32;
+        let looping = ( self.looping_frames.max( 0 ) as u32 ).min( num );
+        let mut f = n;
+        if f >= num {
+            f -= num;
+            if looping > 0 {
+                f %= looping;
+                f += num - looping;
+            } else {
+                f = num - 1;
+            }
+        }
+        let first = self.first_frame.max( 0 ) as u32;
+        if self.reversed {
+            first + num - 1 - f
+        } else {
+            first + f
+        }
+    }
+
+    /// Returns the two model frames to blend and the blend factor at `time`
+    /// seconds after the clip started.
+    pub fn frame_at( &self, time : f32 ) -> ( u32, u32, f32 )
+    {
+        let pos = ( time.max( 0.0 ) * self.fps ).max( 0.0 );
+        // `as` saturates huge or infinite positions at u32::MAX
+        let n = pos.floor() as u32;
+        let frac = if pos.is_finite() { pos - pos.floor() } else { 0.0 };
+        ( self.frame_index( n ), self.frame_index( n.saturating_add( 1 ) ), frac )
+    }
+}
+
+/// A pair of frames to blend and the blend factor, 0 meaning `frame_a`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FrameLerp
+{
+    pub frame_a : usize,
+    pub frame_b : usize,
+    pub t : f32,
+}
+
+impl FrameLerp
+{
+    /// Holds a single frame.
+    pub fn frame( frame : usize ) -> FrameLerp
+    {
+        FrameLerp { frame_a: frame, frame_b: frame, t: 0.0 }
+    }
+
+    /// Frames of `clip` at `time` seconds after it started.
+    pub fn at( clip : &AnimationClip, time : f32 ) -> FrameLerp
+    {
+        let ( a, b, t ) = clip.frame_at( time );
+        FrameLerp { frame_a: a as usize, frame_b: b as usize, t }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Sex
+{
+    Male,
+    Female,
+    Neuter,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Footsteps
+{
+    Normal,
+    Boot,
+    Flesh,
+    Mech,
+    Energy,
+}
+
+pub struct AnimationSet
+{
+    pub sex : Sex,
+    pub footsteps : Footsteps,
+    pub head_offset : Vec3,
+    pub fixed_legs : bool,
+    pub fixed_torso : bool,
+    /// One clip per `AnimationId`, indexed by the id
+    pub clips : Vec<AnimationClip>,
+}
+
+#[derive(Debug)]
+pub enum AnimationError
+{
+    Io( io::Error ),
+    /// A keyword argument or frame number could not be parsed
+    BadValue { line : usize, token : String },
+    /// The file ended before every mandatory animation was listed
+    MissingAnimations { found : usize },
+}
+
+impl fmt::Display for AnimationError
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            AnimationError::Io( ref e ) => write!( f, "I/O error reading animation.cfg: {}", e ),
+            AnimationError::BadValue { line, ref token } =>
+                write!( f, "bad value \"{}\" on line {} of animation.cfg", token, line ),
+            AnimationError::MissingAnimations { found } =>
+                write!( f, "animation.cfg lists {} animations, expected at least {}",
+                        found, TorsoGetFlag as usize ),
+        }
+    }
+}
+
+impl Error for AnimationError
+{
+    fn source( &self ) -> Option<&(dyn Error + 'static)>
+    {
+        match *self {
+            AnimationError::Io( ref e ) => Some( e ),
+            _ => None
+        }
+    }
+}
+
+fn parse_num<T : ::std::str::FromStr>( tok : Option<::script::Token>, line : usize ) -> Result<T, AnimationError>
+{
+    match tok {
+        Some( t ) => t.text.parse().map_err(| _ | AnimationError::BadValue { line: t.line, token: t.text.to_string() } ),
+        None => Err( AnimationError::BadValue { line, token: String::new() } )
+    }
+}
+
+impl AnimationSet
+{
+    pub fn load( fname : String ) -> Result<AnimationSet, AnimationError>
+    {
+        let mut text = String::new();
+        File::open( fname ).and_then(| mut f | f.read_to_string( &mut text ) )
+            .map_err( AnimationError::Io )?;
+        AnimationSet::parse( &text )
+    }
+
+    pub fn parse( text : &str ) -> Result<AnimationSet, AnimationError>
+    {
+        let mut set = AnimationSet {
+            sex: Sex::Male,
+            footsteps: Footsteps::Normal,
+            head_offset: Vec3::new( 0.0, 0.0, 0.0 ),
+            fixed_legs: false,
+            fixed_torso: false,
+            clips: Vec::with_capacity( MAX_ANIMATIONS ),
+        };
+        let mut tok = Tokenizer::new( text );
+
+        // Keywords, up to the first number
+        while let Some( t ) = tok.peek_token( true ) {
+            if t.text.starts_with(| c : char | c.is_ascii_digit() || c == '-' ) {
+                break;
+            }
+            tok.next_token( true );
+            let line = t.line;
+            match t.text.to_lowercase().as_str() {
+                "footsteps" => {
+                    let v = tok.next_token( true );
+                    set.footsteps = match v.as_ref().map(| v | v.text.to_lowercase() ) {
+                        Some( ref s ) if s == "default" || s == "normal" => Footsteps::Normal,
+                        Some( ref s ) if s == "boot" => Footsteps::Boot,
+                        Some( ref s ) if s == "flesh" => Footsteps::Flesh,
+                        Some( ref s ) if s == "mech" => Footsteps::Mech,
+                        Some( ref s ) if s == "energy" => Footsteps::Energy,
+                        _ => return Err( AnimationError::BadValue {
+                            line, token: v.map(| v | v.text.to_string() ).unwrap_or_default()
+                        } )
+                    };
+                },
+                "headoffset" => {
+                    set.head_offset.x = parse_num( tok.next_token( true ), line )?;
+                    set.head_offset.y = parse_num( tok.next_token( true ), line )?;
+                    set.head_offset.z = parse_num( tok.next_token( true ), line )?;
+                },
+                "sex" => {
+                    // The engine only looks at the first letter
+                    let v = tok.next_token( true );
+                    set.sex = match v.as_ref().and_then(| v | v.text.chars().next() ) {
+                        Some( 'f' ) | Some( 'F' ) => Sex::Female,
+                        Some( 'n' ) | Some( 'N' ) => Sex::Neuter,
+                        Some( _ ) => Sex::Male,
+                        None => return Err( AnimationError::BadValue { line, token: String::new() } )
+                    };
+                },
+                "fixedlegs" => set.fixed_legs = true,
+                "fixedtorso" => set.fixed_torso = true,
+                // The engine warns about and skips unknown keywords
+                _ => {}
+            }
+        }
+
+        // Animations, in AnimationId order
+        let mut skip = 0;
+        for &id in ALL_ANIMATIONS.iter() {
+            let first = match tok.next_token( true ) {
+                Some( t ) => t,
+                None => {
+                    if id as usize >= TorsoGetFlag as usize {
+                        // Pre Team Arena models fall back to the gesture
+                        let gesture = set.clips[TorsoGesture as usize];
+                        set.clips.push( gesture );
+                        continue;
+                    }
+                    return Err( AnimationError::MissingAnimations { found: set.clips.len() } );
+                }
+            };
+            let line = first.line;
+            let mut first_frame : i32 = parse_num( Some( first ), line )?;
+            let num_frames : i32 = parse_num( tok.next_token( true ), line )?;
+            let looping_frames : i32 = parse_num( tok.next_token( true ), line )?;
+            let mut fps : f32 = parse_num( tok.next_token( true ), line )?;
+            if fps == 0.0 {
+                fps = 1.0;
+            }
+
+            // lower.md3 does not contain the torso-only frames, so legs
+            // animations are rebased to skip them
+            if id == LegsWalkCr {
+                skip = first_frame - set.clips[TorsoGesture as usize].first_frame;
+            }
+            if id.is_legs() {
+                first_frame -= skip;
+            }
+
+            // a loop cannot be longer than the clip it repeats the tail of
+            set.clips.push( AnimationClip {
+                first_frame,
+                num_frames: num_frames.abs(),
+                looping_frames: looping_frames.clamp( 0, num_frames.abs() ),
+                fps,
+                reversed: num_frames < 0,
+            } );
+        }
+
+        Ok( set )
+    }
+
+    pub fn clip( &self, id : AnimationId ) -> &AnimationClip
+    {
+        &self.clips[id as usize]
+    }
+}
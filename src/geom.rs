@@ -0,0 +1,162 @@
+// GL ready meshes built from an Md3Model.
+//
+// Every frame becomes one vertex/index buffer holding all surfaces, with a
+// submesh per surface so the caller can bind that surface's shader for its
+// index range. Models with more than one frame convert to a
+// `GLVertexAnimatedMesh` holding one such mesh per frame.
+//
+// Two breaks from the meshes the crate used to build: `GLStaticMesh::indices`
+// is `Vec<u32>` instead of `Vec<u16>`, since a merged frame can pass 65535
+// vertices, and `create_gl_ready_mesh` takes `&self` instead of `&mut self`,
+// since conversion never changed the model.
+
+use md3::Md3Model;
+
+#[derive(Copy, Clone)]
+pub struct GLVertex
+{
+    pub position: [f32; 3],
+    pub normal:   [f32; 3],
+    pub uv:       [f32; 2]
+}
+
+/// A contiguous run of indices drawn with one surface's shader.
+#[derive(Clone, Debug, PartialEq)]
+pub struct GLSubmesh
+{
+    pub name:        String,
+    pub shader:      String,
+    pub first_index: usize,
+    pub index_count: usize
+}
+
+/// All surfaces of one frame merged into a single vertex/index
+/// buffer, with `submeshes` giving the per-surface index ranges.
+pub struct GLStaticMesh
+{
+    pub indices:   Vec<u32>,
+    pub vertices:  Vec<GLVertex>,
+    pub submeshes: Vec<GLSubmesh>
+}
+
+/// One merged mesh per model frame, in frame order. Every frame has
+/// the same indices and submeshes; only the vertices differ.
+pub struct GLVertexAnimatedMesh
+{
+    pub frames : Vec<GLStaticMesh>
+}
+
+pub enum GLReadyMesh{
+    VertexAnimated( GLVertexAnimatedMesh ),
+    Static( GLStaticMesh ),
+    Corrupted( String ) // Failed on loading
+}
+
+pub struct UV{
+    pub u: f32, pub v: f32
+}
+
+pub trait CreateGLReadyMesh {
+    fn create_gl_ready_mesh( &self ) -> GLReadyMesh;
+}
+
+impl GLStaticMesh
+{
+    pub fn empty() -> GLStaticMesh
+    {
+        GLStaticMesh{
+            indices: vec![], vertices: vec![], submeshes: vec![]
+        }
+    }
+
+    /// Merges every surface of `frame` into one mesh, offsetting each
+    /// surface's indices by the vertices already emitted. Returns an
+    /// error naming the first surface that lacks the frame or has an
+    /// index outside its vertices.
+    pub fn from_frame( model : &Md3Model, frame : usize ) -> Result<GLStaticMesh, String>
+    {
+        let mut smesh = GLStaticMesh::empty();
+        for surf in model.surfaces.iter() {
+            let xyzn = surf.frame_vertices( frame );
+            if xyzn.len() != surf.data.st_data.len() {
+                return Err( format!( "Surface '{}' has no vertices for frame {}",
+                                     surf.header.name_str(), frame ) );
+            }
+
+            if let Some( &i ) = surf.data.triangles.iter().flat_map(| t | t.indices.iter() )
+                .find(| &&i | i < 0 || i as usize >= xyzn.len() ) {
+                return Err( format!( "Surface '{}' has index {} outside its {} vertices",
+                                     surf.header.name_str(), i, xyzn.len() ) );
+            }
+
+            let base = smesh.vertices.len() as u32;
+            smesh.vertices.extend( xyzn.iter()
+                .zip( surf.data.st_data.iter() )
+                .map(|(xyzn, st)|{
+                    GLVertex{
+                        position:  xyzn.decode_xyz(),
+                        normal:    xyzn.decode_normal(),
+                        uv:        [ st.st[0]  ,  1.0f32 - st.st[1] - st.st[1].floor()]
+                    }
+                }) );
+
+            let first_index = smesh.indices.len();
+            smesh.indices.reserve( surf.data.triangles.len() * 3 );
+            for tri in surf.data.triangles.iter() {
+                for &i in tri.indices.iter() {
+                    smesh.indices.push( base + i as u32 );
+                }
+            }
+
+            smesh.submeshes.push( GLSubmesh {
+                name:        surf.header.name_str().to_string(),
+                shader:      surf.data.shaders.first()
+                                 .map( | s | s.name_str().to_string() )
+                                 .unwrap_or_default(),
+                first_index,
+                index_count: smesh.indices.len() - first_index
+            } );
+        }
+        Ok( smesh )
+    }
+
+}
+
+impl GLVertex
+{
+    pub fn null() -> GLVertex
+    {
+        GLVertex {
+            position: [0 as f32; 3],
+            normal:   [0 as f32; 3],
+            uv:       [0 as f32; 2]
+        }
+    }
+}
+
+impl CreateGLReadyMesh for Md3Model
+{
+    fn create_gl_ready_mesh( &self ) -> GLReadyMesh
+    {
+        // Convert to GL ready format
+        let frame_count = self.header.frame_count.max( 0 ) as usize;
+        if frame_count > 1 {
+            // VertexAnimated mesh will be produced, one merged mesh per frame
+            let mut amesh = GLVertexAnimatedMesh { frames: Vec::with_capacity( frame_count ) };
+            for frame in 0 .. frame_count {
+                match GLStaticMesh::from_frame( self, frame ) {
+                    Ok( smesh ) => amesh.frames.push( smesh ),
+                    Err( e ) => return GLReadyMesh::Corrupted( e )
+                }
+            }
+            return GLReadyMesh::VertexAnimated( amesh );
+        }else if frame_count == 1{
+            // Static mesh will be produced
+            return match GLStaticMesh::from_frame( self, 0 ) {
+                Ok( smesh ) => GLReadyMesh::Static( smesh ),
+                Err( e ) => GLReadyMesh::Corrupted( e )
+            };
+        }
+        GLReadyMesh::Corrupted(String::from("Cannot make GL ready mesh from MD3 model!"))
+    }
+}
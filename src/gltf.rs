@@ -0,0 +1,692 @@
+// glTF 2.0 export.
+//
+// Every surface becomes a primitive of a single mesh. Frame 0 is the base
+// geometry and every later frame is a morph target holding position and
+// normal deltas, driven by a "weights" animation channel that steps from one
+// target to the next. Tags become child nodes animated with translation and
+// rotation channels, and each distinct shader name becomes a material.
+//
+// Geometry stays in Quake's Z-up space; the root node carries the rotation
+// to glTF's Y-up. Triangles are rewound from MD3's clockwise fronts to
+// glTF's counter-clockwise ones.
+//
+// `Gltf::to_model` imports triangle primitives back into an `Md3Model`, with
+// morph targets turned into frames and triangles rewound to MD3's clockwise
+// fronts.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use byteorder::{ByteOrder, LittleEndian, WriteBytesExt};
+use serde_json::Value;
+use import::{build_model, ImportError, MeshSurface};
+use math::{Transform, Vec3};
+use md3::Md3Model;
+use skin::Skin;
+
+const GL_FLOAT : u32 = 5126;
+const GL_BYTE : u32 = 5120;
+const GL_UNSIGNED_BYTE : u32 = 5121;
+const GL_SHORT : u32 = 5122;
+const GL_UNSIGNED_SHORT : u32 = 5123;
+const GL_UNSIGNED_INT : u32 = 5125;
+const GL_ARRAY_BUFFER : u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER : u32 = 34963;
+
+const GLB_MAGIC : u32 = 0x4654_6C67; // "glTF"
+const GLB_CHUNK_JSON : u32 = 0x4E4F_534A;
+const GLB_CHUNK_BIN : u32 = 0x004E_4942;
+
+/// An exported document: the glTF JSON and the binary buffer it refers to.
+pub struct Gltf
+{
+    /// Document without a buffer URI; one is added when writing a .gltf
+    pub json : Value,
+    pub bin : Vec<u8>,
+}
+
+/// Accumulates buffer views and accessors over a single binary buffer.
+struct Builder
+{
+    bin : Vec<u8>,
+    views : Vec<Value>,
+    accessors : Vec<Value>,
+}
+
+impl Builder
+{
+    fn push_view( &mut self, bytes : &[u8], target : Option<u32> ) -> usize
+    {
+        while !self.bin.len().is_multiple_of( 4 ) {
+            self.bin.push( 0 );
+        }
+        let mut view = json!({
+            "buffer": 0,
+            "byteOffset": self.bin.len(),
+            "byteLength": bytes.len(),
+        });
+        if let Some( t ) = target {
+            view["target"] = json!( t );
+        }
+        self.bin.extend_from_slice( bytes );
+        self.views.push( view );
+        self.views.len() - 1
+    }
+
+    fn push_accessor( &mut self, accessor : Value ) -> usize
+    {
+        self.accessors.push( accessor );
+        self.accessors.len() - 1
+    }
+
+    fn vec3s( &mut self, data : &[[f32; 3]], with_bounds : bool ) -> usize
+    {
+        let mut bytes = Vec::with_capacity( data.len() * 12 );
+        for v in data.iter() {
+            for c in v.iter() {
+                bytes.write_f32::<LittleEndian>( *c ).unwrap();
+            }
+        }
+        let view = self.push_view( &bytes, Some( GL_ARRAY_BUFFER ) );
+        let mut acc = json!({
+            "bufferView": view, "componentType": GL_FLOAT, "count": data.len(), "type": "VEC3"
+        });
+        if with_bounds {
+            let mut min = [ f32::MAX; 3 ];
+            let mut max = [ f32::MIN; 3 ];
+            for v in data.iter() {
+                for i in 0 .. 3 {
+                    min[i] = min[i].min( v[i] );
+                    max[i] = max[i].max( v[i] );
+                }
+            }
+            if data.is_empty() {
+                min = [0.0; 3];
+                max = [0.0; 3];
+            }
+            acc["min"] = json!( min );
+            acc["max"] = json!( max );
+        }
+        self.push_accessor( acc )
+    }
+
+    /// Tightly packed floats; `kind` is the accessor type ("SCALAR", "VEC2", "VEC4").
+    fn floats( &mut self, data : &[f32], kind : &str, width : usize, target : Option<u32> ) -> usize
+    {
+        let mut bytes = Vec::with_capacity( data.len() * 4 );
+        for c in data.iter() {
+            bytes.write_f32::<LittleEndian>( *c ).unwrap();
+        }
+        let view = self.push_view( &bytes, target );
+        self.push_accessor( json!({
+            "bufferView": view, "componentType": GL_FLOAT, "count": data.len() / width, "type": kind
+        }) )
+    }
+
+    fn indices( &mut self, data : &[u32] ) -> usize
+    {
+        let mut bytes = Vec::with_capacity( data.len() * 4 );
+        for i in data.iter() {
+            bytes.write_u32::<LittleEndian>( *i ).unwrap();
+        }
+        let view = self.push_view( &bytes, Some( GL_ELEMENT_ARRAY_BUFFER ) );
+        self.push_accessor( json!({
+            "bufferView": view, "componentType": GL_UNSIGNED_INT, "count": data.len(), "type": "SCALAR"
+        }) )
+    }
+}
+
+/// Rotation part of a transform as a unit quaternion `[x, y, z, w]`.
+fn to_quaternion( t : &Transform ) -> [f32; 4]
+{
+    // m[row][col], the axes are the matrix columns
+    let a = &t.axis;
+    let m = [
+        [ a[0].x, a[1].x, a[2].x ],
+        [ a[0].y, a[1].y, a[2].y ],
+        [ a[0].z, a[1].z, a[2].z ],
+    ];
+    let trace = m[0][0] + m[1][1] + m[2][2];
+    let q = if trace > 0.0 {
+        let s = ( trace + 1.0 ).sqrt() * 2.0;
+        [ ( m[2][1] - m[1][2] ) / s, ( m[0][2] - m[2][0] ) / s, ( m[1][0] - m[0][1] ) / s, 0.25 * s ]
+    } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+        let s = ( 1.0 + m[0][0] - m[1][1] - m[2][2] ).sqrt() * 2.0;
+        [ 0.25 * s, ( m[0][1] + m[1][0] ) / s, ( m[0][2] + m[2][0] ) / s, ( m[2][1] - m[1][2] ) / s ]
+    } else if m[1][1] > m[2][2] {
+        let s = ( 1.0 + m[1][1] - m[0][0] - m[2][2] ).sqrt() * 2.0;
+        [ ( m[0][1] + m[1][0] ) / s, 0.25 * s, ( m[1][2] + m[2][1] ) / s, ( m[0][2] - m[2][0] ) / s ]
+    } else {
+        let s = ( 1.0 + m[2][2] - m[0][0] - m[1][1] ).sqrt() * 2.0;
+        [ ( m[0][2] + m[2][0] ) / s, ( m[1][2] + m[2][1] ) / s, 0.25 * s, ( m[1][0] - m[0][1] ) / s ]
+    };
+    let len = ( q[0] * q[0] + q[1] * q[1] + q[2] * q[2] + q[3] * q[3] ).sqrt();
+    [ q[0] / len, q[1] / len, q[2] / len, q[3] / len ]
+}
+
+impl Gltf
+{
+    /// Converts `model`, binding materials through `skin` when given and
+    /// timing the animation at `fps` frames per second. Fails with
+    /// `InvalidInput` if `fps` is not a positive, finite rate or a triangle
+    /// index lies outside its surface.
+    pub fn from_model( model : &Md3Model, skin : Option<&Skin>, fps : f32 ) -> io::Result<Gltf>
+    {
+        if !( fps.is_finite() && fps > 0.0 ) {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput,
+                                        format!( "fps must be positive and finite, got {}", fps ) ) );
+        }
+        let mut b = Builder { bin: vec![], views: vec![], accessors: vec![] };
+
+        // Only frames present in every surface can become morph targets
+        let frame_count = model.surfaces.iter()
+            .map(| s | {
+                let verts = s.header.vertex_count.max( 0 ) as usize;
+                s.data.xyz_normals.len().checked_div( verts ).unwrap_or( model.frames.len() )
+            })
+            .fold( model.frames.len().max( 1 ), | a, n | a.min( n ) )
+            .max( 1 );
+
+        let mut materials : Vec<Value> = vec![];
+        let mut images : Vec<Value> = vec![];
+        let mut material_ids : HashMap<String, usize> = HashMap::new();
+        let mut primitives : Vec<Value> = vec![];
+
+        for surf in model.surfaces.iter() {
+            let base = surf.frame_vertices( 0 );
+            let positions : Vec<[f32; 3]> = base.iter().map(| v | v.decode_xyz() ).collect();
+            let normals : Vec<[f32; 3]> = base.iter().map(| v | v.decode_normal() ).collect();
+            let uvs : Vec<f32> = surf.data.st_data.iter().take( base.len() )
+                .flat_map(| st | st.st.to_vec() ).collect();
+            // MD3 fronts wind clockwise, glTF's counter-clockwise
+            let mut indices : Vec<u32> = Vec::with_capacity( surf.data.triangles.len() * 3 );
+            for t in surf.data.triangles.iter() {
+                for &i in [ t.indices[0], t.indices[2], t.indices[1] ].iter() {
+                    if i < 0 || i as usize >= base.len() {
+                        return Err( io::Error::new( io::ErrorKind::InvalidInput,
+                                                    format!( "surface {} has index {} outside its {} vertices",
+                                                             surf.header.name_str(), i, base.len() ) ) );
+                    }
+                    indices.push( i as u32 );
+                }
+            }
+
+            let mut attributes = json!({
+                "POSITION": b.vec3s( &positions, true ),
+                "NORMAL": b.vec3s( &normals, false ),
+            });
+            if uvs.len() == base.len() * 2 {
+                attributes["TEXCOORD_0"] = json!( b.floats( &uvs, "VEC2", 2, Some( GL_ARRAY_BUFFER ) ) );
+            }
+
+            let mut targets = vec![];
+            for f in 1 .. frame_count {
+                let verts = surf.frame_vertices( f );
+                let dpos : Vec<[f32; 3]> = verts.iter().zip( positions.iter() )
+                    .map(|( v, p )| { let q = v.decode_xyz(); [ q[0] - p[0], q[1] - p[1], q[2] - p[2] ] })
+                    .collect();
+                let dnorm : Vec<[f32; 3]> = verts.iter().zip( normals.iter() )
+                    .map(|( v, n )| { let q = v.decode_normal(); [ q[0] - n[0], q[1] - n[1], q[2] - n[2] ] })
+                    .collect();
+                targets.push( json!({
+                    "POSITION": b.vec3s( &dpos, true ),
+                    "NORMAL": b.vec3s( &dnorm, false ),
+                }) );
+            }
+
+            let mut prim = json!({
+                "attributes": attributes,
+                "indices": b.indices( &indices ),
+                "mode": 4,
+            });
+            if !targets.is_empty() {
+                prim["targets"] = json!( targets );
+            }
+            if let Some( shader ) = surf.resolve_shader( skin ) {
+                let next = materials.len();
+                let id = *material_ids.entry( shader.to_string() ).or_insert( next );
+                if id == next {
+                    images.push( json!({ "uri": shader }) );
+                    materials.push( json!({
+                        "name": shader,
+                        "pbrMetallicRoughness": {
+                            "baseColorTexture": { "index": images.len() - 1 },
+                            "metallicFactor": 0.0,
+                        },
+                    }) );
+                }
+                prim["material"] = json!( id );
+            }
+            primitives.push( prim );
+        }
+
+        let mut nodes = vec![];
+        let mut root_children = vec![];
+        let mut channels = vec![];
+        let mut samplers = vec![];
+        let times : Vec<f32> = ( 0 .. frame_count ).map(| f | f as f32 / fps ).collect();
+        let time_acc = if frame_count > 1 {
+            let acc = b.floats( &times, "SCALAR", 1, None );
+            b.accessors[acc]["min"] = json!( [ times[0] ] );
+            b.accessors[acc]["max"] = json!( [ times[frame_count - 1] ] );
+            Some( acc )
+        } else {
+            None
+        };
+
+        let mut mesh = json!({ "name": model.name_str(), "primitives": primitives });
+        let morphs = frame_count - 1;
+        if morphs > 0 {
+            mesh["weights"] = json!( vec![0.0f32; morphs] );
+        }
+        nodes.push( json!({ "name": model.name_str(), "mesh": 0 }) );
+        root_children.push( 0 );
+        if let Some( time_acc ) = time_acc {
+            // Keyframe f shows target f - 1 at full weight, keyframe 0 the base mesh
+            let mut weights = vec![0.0f32; frame_count * morphs];
+            for f in 1 .. frame_count {
+                weights[f * morphs + f - 1] = 1.0;
+            }
+            let out = b.floats( &weights, "SCALAR", 1, None );
+            samplers.push( json!({ "input": time_acc, "output": out, "interpolation": "LINEAR" }) );
+            channels.push( json!({ "sampler": samplers.len() - 1, "target": { "node": 0, "path": "weights" } }) );
+        }
+
+        for ( i, tag ) in model.frame_tags( 0 ).iter().enumerate() {
+            let tr = tag.transform();
+            let node = nodes.len();
+            nodes.push( json!({
+                "name": tag.name_str(),
+                "translation": tr.origin.to_array(),
+                "rotation": to_quaternion( &tr ),
+            }) );
+            root_children.push( node );
+            if let Some( time_acc ) = time_acc {
+                let tags : Vec<Transform> = ( 0 .. frame_count )
+                    .map(| f | model.frame_tags( f ).get( i ).map(| t | t.transform() ).unwrap_or( tr ) )
+                    .collect();
+                let trans : Vec<f32> = tags.iter().flat_map(| t | t.origin.to_array().to_vec() ).collect();
+                let rots : Vec<f32> = tags.iter().flat_map(| t | to_quaternion( t ).to_vec() ).collect();
+                let trans_acc = b.floats( &trans, "VEC3", 3, None );
+                let rot_acc = b.floats( &rots, "VEC4", 4, None );
+                for &( acc, path ) in [ ( trans_acc, "translation" ), ( rot_acc, "rotation" ) ].iter() {
+                    samplers.push( json!({ "input": time_acc, "output": acc, "interpolation": "LINEAR" }) );
+                    channels.push( json!({ "sampler": samplers.len() - 1, "target": { "node": node, "path": path } }) );
+                }
+            }
+        }
+
+        // Quake is Z-up, glTF is Y-up: -90 degrees about X
+        let half = ::std::f32::consts::FRAC_1_SQRT_2;
+        nodes.push( json!({ "name": "md3_root", "rotation": [ -half, 0.0, 0.0, half ], "children": root_children }) );
+        let root = nodes.len() - 1;
+
+        let mut doc = json!({
+            "asset": { "version": "2.0", "generator": "md3-rs" },
+            "scene": 0,
+            "scenes": [ { "nodes": [ root ] } ],
+            "nodes": nodes,
+            "meshes": [ mesh ],
+            "buffers": [ { "byteLength": b.bin.len() } ],
+            "bufferViews": b.views,
+            "accessors": b.accessors,
+        });
+        if !materials.is_empty() {
+            let textures : Vec<Value> = ( 0 .. images.len() ).map(| i | json!({ "source": i }) ).collect();
+            doc["materials"] = json!( materials );
+            doc["textures"] = json!( textures );
+            doc["images"] = json!( images );
+        }
+        if !channels.is_empty() {
+            doc["animations"] = json!( [ { "name": "md3_frames", "channels": channels, "samplers": samplers } ] );
+        }
+
+        Ok( Gltf { json: doc, bin: b.bin } )
+    }
+
+    /// Writes the .gltf JSON, pointing its buffer at `bin_uri`.
+    pub fn write_gltf<W : Write>( &self, out : &mut W, bin_uri : &str ) -> io::Result<()>
+    {
+        let mut doc = self.json.clone();
+        doc["buffers"][0]["uri"] = json!( bin_uri );
+        serde_json::to_writer_pretty( out, &doc ).map_err( io::Error::from )
+    }
+
+    /// Writes a self-contained binary .glb.
+    pub fn write_glb<W : Write>( &self, out : &mut W ) -> io::Result<()>
+    {
+        let mut json = serde_json::to_vec( &self.json ).map_err( io::Error::from )?;
+        while !json.len().is_multiple_of( 4 ) {
+            json.push( b' ' );
+        }
+        let mut bin = self.bin.clone();
+        while !bin.len().is_multiple_of( 4 ) {
+            bin.push( 0 );
+        }
+        let total = 12 + 8 + json.len() + 8 + bin.len();
+
+        out.write_u32::<LittleEndian>( GLB_MAGIC )?;
+        out.write_u32::<LittleEndian>( 2 )?;
+        out.write_u32::<LittleEndian>( total as u32 )?;
+        out.write_u32::<LittleEndian>( json.len() as u32 )?;
+        out.write_u32::<LittleEndian>( GLB_CHUNK_JSON )?;
+        out.write_all( &json )?;
+        out.write_u32::<LittleEndian>( bin.len() as u32 )?;
+        out.write_u32::<LittleEndian>( GLB_CHUNK_BIN )?;
+        out.write_all( &bin )
+    }
+
+    /// Saves `fname` (.gltf) and its buffer next to it with a .bin extension.
+    pub fn save( &self, fname : String ) -> io::Result<()>
+    {
+        let bin_path = Path::new( &fname ).with_extension( "bin" );
+        let bin_uri = bin_path.file_name().map(| n | n.to_string_lossy().into_owned() ).unwrap_or_default();
+        File::create( &bin_path )?.write_all( &self.bin )?;
+        self.write_gltf( &mut io::BufWriter::new( File::create( &fname )? ), &bin_uri )
+    }
+
+    pub fn save_glb( &self, fname : String ) -> io::Result<()>
+    {
+        self.write_glb( &mut io::BufWriter::new( File::create( fname )? ) )
+    }
+}
+
+// Importing
+
+fn gltf_err( message : String ) -> ImportError
+{
+    ImportError::Invalid { message }
+}
+
+fn decode_base64( data : &str ) -> Result<Vec<u8>, ImportError>
+{
+    let mut out = Vec::with_capacity( data.len() * 3 / 4 );
+    let mut acc : u32 = 0;
+    let mut bits = 0;
+    for c in data.bytes() {
+        let v = match c {
+            b'A' ..= b'Z' => c - b'A',
+            b'a' ..= b'z' => c - b'a' + 26,
+            b'0' ..= b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            _ if c.is_ascii_whitespace() => continue,
+            _ => return Err( gltf_err( "bad base64 data URI".to_string() ) )
+        };
+        acc = ( acc << 6 ) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push( ( acc >> bits ) as u8 );
+        }
+    }
+    Ok( out )
+}
+
+/// Column-major 4x4 product `a * b`.
+fn mat_mul( a : &[[f32; 4]; 4], b : &[[f32; 4]; 4] ) -> [[f32; 4]; 4]
+{
+    let mut m = [[0.0f32; 4]; 4];
+    for c in 0 .. 4 {
+        for r in 0 .. 4 {
+            m[c][r] = ( 0 .. 4 ).map(| k | a[k][r] * b[c][k] ).sum();
+        }
+    }
+    m
+}
+
+/// Local matrix of a node from `matrix` or translation/rotation/scale.
+fn node_matrix( node : &Value ) -> [[f32; 4]; 4]
+{
+    let num = | v : &Value, d : f32 | v.as_f64().map_or( d, | f | f as f32 );
+    if let Some( m ) = node["matrix"].as_array() {
+        let mut out = [[0.0f32; 4]; 4];
+        for i in 0 .. 16 {
+            out[i / 4][i % 4] = m.get( i ).map_or( 0.0, | v | num( v, 0.0 ) );
+        }
+        return out;
+    }
+    let t = &node["translation"];
+    let r = &node["rotation"];
+    let s = &node["scale"];
+    let ( tx, ty, tz ) = ( num( &t[0], 0.0 ), num( &t[1], 0.0 ), num( &t[2], 0.0 ) );
+    let ( x, y, z, w ) = ( num( &r[0], 0.0 ), num( &r[1], 0.0 ), num( &r[2], 0.0 ), num( &r[3], 1.0 ) );
+    let ( sx, sy, sz ) = ( num( &s[0], 1.0 ), num( &s[1], 1.0 ), num( &s[2], 1.0 ) );
+    [
+        [ ( 1.0 - 2.0 * ( y * y + z * z ) ) * sx, 2.0 * ( x * y + z * w ) * sx, 2.0 * ( x * z - y * w ) * sx, 0.0 ],
+        [ 2.0 * ( x * y - z * w ) * sy, ( 1.0 - 2.0 * ( x * x + z * z ) ) * sy, 2.0 * ( y * z + x * w ) * sy, 0.0 ],
+        [ 2.0 * ( x * z + y * w ) * sz, 2.0 * ( y * z - x * w ) * sz, ( 1.0 - 2.0 * ( x * x + y * y ) ) * sz, 0.0 ],
+        [ tx, ty, tz, 1.0 ],
+    ]
+}
+
+/// Applies a world matrix and converts glTF's Y-up to Quake's Z-up.
+fn to_quake( m : &[[f32; 4]; 4], v : [f32; 3], w : f32 ) -> [f32; 3]
+{
+    let p = [
+        m[0][0] * v[0] + m[1][0] * v[1] + m[2][0] * v[2] + m[3][0] * w,
+        m[0][1] * v[0] + m[1][1] * v[1] + m[2][1] * v[2] + m[3][1] * w,
+        m[0][2] * v[0] + m[1][2] * v[1] + m[2][2] * v[2] + m[3][2] * w,
+    ];
+    [ p[0], -p[2], p[1] ]
+}
+
+impl Gltf
+{
+    /// Parses a binary .glb container.
+    pub fn from_glb( bytes : &[u8] ) -> Result<Gltf, ImportError>
+    {
+        if bytes.len() < 20 || LittleEndian::read_u32( &bytes[0..4] ) != GLB_MAGIC {
+            return Err( gltf_err( "not a GLB file".to_string() ) );
+        }
+        let mut json = None;
+        let mut bin = vec![];
+        let mut pos = 12;
+        while pos + 8 <= bytes.len() {
+            let len = LittleEndian::read_u32( &bytes[pos..pos + 4] ) as usize;
+            let kind = LittleEndian::read_u32( &bytes[pos + 4..pos + 8] );
+            let chunk = bytes.get( pos + 8 .. pos + 8 + len )
+                .ok_or_else(|| gltf_err( "truncated GLB chunk".to_string() ) )?;
+            if kind == GLB_CHUNK_JSON {
+                json = Some( serde_json::from_slice( chunk ).map_err(| e | gltf_err( e.to_string() ) )? );
+            } else if kind == GLB_CHUNK_BIN && bin.is_empty() {
+                bin = chunk.to_vec();
+            }
+            pos += 8 + len;
+        }
+        let json = json.ok_or_else(|| gltf_err( "GLB has no JSON chunk".to_string() ) )?;
+        Ok( Gltf { json, bin } )
+    }
+
+    /// Loads a .glb, or a .gltf whose first buffer is a data URI or a file
+    /// next to it. Only the first buffer is used.
+    pub fn load( fname : String ) -> Result<Gltf, ImportError>
+    {
+        let mut bytes = vec![];
+        File::open( &fname )?.read_to_end( &mut bytes )?;
+        if bytes.starts_with( b"glTF" ) {
+            return Gltf::from_glb( &bytes );
+        }
+        let json : Value = serde_json::from_slice( &bytes ).map_err(| e | gltf_err( e.to_string() ) )?;
+        let bin = match json["buffers"][0]["uri"].as_str() {
+            None => vec![],
+            Some( uri ) if uri.starts_with( "data:" ) => {
+                let data = uri.split( ',' ).nth( 1 ).unwrap_or( "" );
+                decode_base64( data )?
+            },
+            Some( uri ) => {
+                let path = Path::new( &fname ).with_file_name( uri );
+                let mut bin = vec![];
+                File::open( path )?.read_to_end( &mut bin )?;
+                bin
+            }
+        };
+        Ok( Gltf { json, bin } )
+    }
+
+    /// Reads accessor `index` as rows of `width` values, converted to f32.
+    /// Normalized integer accessors are mapped onto 0..1 (unsigned) or
+    /// -1..1 (signed) as the glTF spec describes.
+    fn read_accessor( &self, index : &Value, width : usize ) -> Result<Vec<Vec<f32>>, ImportError>
+    {
+        let idx = index.as_u64().ok_or_else(|| gltf_err( "missing accessor".to_string() ) )? as usize;
+        let acc = &self.json["accessors"][idx];
+        if acc.get( "sparse" ).is_some() {
+            return Err( gltf_err( format!( "accessor {} is sparse", idx ) ) );
+        }
+        let count = acc["count"].as_u64().unwrap_or( 0 ) as usize;
+        let ctype = acc["componentType"].as_u64().unwrap_or( 0 ) as u32;
+        let normalized = acc["normalized"].as_bool().unwrap_or( false );
+        let csize = match ctype {
+            GL_BYTE | GL_UNSIGNED_BYTE => 1,
+            GL_SHORT | GL_UNSIGNED_SHORT => 2,
+            GL_UNSIGNED_INT | GL_FLOAT => 4,
+            _ => return Err( gltf_err( format!( "accessor {} has unsupported component type {}", idx, ctype ) ) )
+        };
+        let view = &self.json["bufferViews"][acc["bufferView"].as_u64().unwrap_or( 0 ) as usize];
+        let start = view["byteOffset"].as_u64().unwrap_or( 0 ) as usize + acc["byteOffset"].as_u64().unwrap_or( 0 ) as usize;
+        let stride = view["byteStride"].as_u64().map_or( csize * width, | s | s as usize );
+
+        let mut rows = Vec::with_capacity( count );
+        for i in 0 .. count {
+            let mut row = Vec::with_capacity( width );
+            for c in 0 .. width {
+                let at = start + i * stride + c * csize;
+                let b = self.bin.get( at .. at + csize )
+                    .ok_or_else(|| gltf_err( format!( "accessor {} runs past the buffer", idx ) ) )?;
+                row.push( match ( ctype, normalized ) {
+                    ( GL_BYTE, true ) => ( b[0] as i8 as f32 / 127.0 ).max( -1.0 ),
+                    ( GL_BYTE, false ) => b[0] as i8 as f32,
+                    ( GL_UNSIGNED_BYTE, true ) => b[0] as f32 / 255.0,
+                    ( GL_UNSIGNED_BYTE, false ) => b[0] as f32,
+                    ( GL_SHORT, true ) => ( LittleEndian::read_i16( b ) as f32 / 32767.0 ).max( -1.0 ),
+                    ( GL_SHORT, false ) => LittleEndian::read_i16( b ) as f32,
+                    ( GL_UNSIGNED_SHORT, true ) => LittleEndian::read_u16( b ) as f32 / 65535.0,
+                    ( GL_UNSIGNED_SHORT, false ) => LittleEndian::read_u16( b ) as f32,
+                    ( GL_UNSIGNED_INT, _ ) => LittleEndian::read_u32( b ) as f32,
+                    _ => LittleEndian::read_f32( b ),
+                } );
+            }
+            rows.push( row );
+        }
+        Ok( rows )
+    }
+
+    fn read_vec3s( &self, index : &Value ) -> Result<Vec<[f32; 3]>, ImportError>
+    {
+        Ok( self.read_accessor( index, 3 )?.into_iter().map(| r | [ r[0], r[1], r[2] ] ).collect() )
+    }
+
+    fn primitive_surface( &self, prim : &Value, world : &[[f32; 4]; 4], name : String ) -> Result<MeshSurface, ImportError>
+    {
+        let attrs = &prim["attributes"];
+        let base = self.read_vec3s( &attrs["POSITION"] )?;
+        let base_n = if attrs["NORMAL"].is_null() { vec![ [0.0, 0.0, 1.0]; base.len() ] } else { self.read_vec3s( &attrs["NORMAL"] )? };
+        let uvs : Vec<[f32; 2]> = if attrs["TEXCOORD_0"].is_null() {
+            vec![ [0.0, 0.0]; base.len() ]
+        } else {
+            self.read_accessor( &attrs["TEXCOORD_0"], 2 )?.into_iter().map(| r | [ r[0], r[1] ] ).collect()
+        };
+        // glTF fronts wind counter-clockwise, MD3's clockwise
+        let triangles : Vec<[u32; 3]> = if prim["indices"].is_null() {
+            ( 0 .. base.len() as u32 / 3 ).map(| i | [ i * 3, i * 3 + 2, i * 3 + 1 ] ).collect()
+        } else {
+            self.read_accessor( &prim["indices"], 1 )?.chunks( 3 ).filter(| c | c.len() == 3 )
+                .map(| c | [ c[0][0] as u32, c[2][0] as u32, c[1][0] as u32 ] ).collect()
+        };
+        if triangles.iter().any(| t | t.iter().any(| &i | i as usize >= base.len() ) ) {
+            return Err( gltf_err( format!( "{} has an index past its vertices", name ) ) );
+        }
+        // Every attribute must describe the same vertices as POSITION
+        let check_count = | what : &str, count : usize | if count == base.len() {
+            Ok( () )
+        } else {
+            Err( gltf_err( format!( "{} has {} {} values for {} positions", name, count, what, base.len() ) ) )
+        };
+        check_count( "NORMAL", base_n.len() )?;
+        check_count( "TEXCOORD_0", uvs.len() )?;
+
+        // Frame 0 is the base mesh, frame n adds morph target n - 1
+        let mut frames = vec![ ( base.clone(), base_n.clone() ) ];
+        if let Some( targets ) = prim["targets"].as_array() {
+            for t in targets.iter() {
+                let dp = if t["POSITION"].is_null() { vec![ [0.0; 3]; base.len() ] } else { self.read_vec3s( &t["POSITION"] )? };
+                let dn = if t["NORMAL"].is_null() { vec![ [0.0; 3]; base.len() ] } else { self.read_vec3s( &t["NORMAL"] )? };
+                check_count( "morph target POSITION", dp.len() )?;
+                check_count( "morph target NORMAL", dn.len() )?;
+                let add = | a : &[[f32; 3]], d : &[[f32; 3]] | -> Vec<[f32; 3]> {
+                    a.iter().zip( d.iter() ).map(|( a, d )| [ a[0] + d[0], a[1] + d[1], a[2] + d[2] ] ).collect()
+                };
+                frames.push( ( add( &base, &dp ), add( &base_n, &dn ) ) );
+            }
+        }
+
+        let material = match prim["material"].as_u64() {
+            Some( m ) => self.json["materials"][m as usize]["name"].as_str()
+                .map_or_else(|| format!( "material{}", m ), | s | s.to_string() ),
+            None => String::new()
+        };
+
+        Ok( MeshSurface {
+            name,
+            material,
+            positions: frames.iter().map(| f | f.0.iter().map(| p | to_quake( world, *p, 1.0 ) ).collect() ).collect(),
+            normals: frames.iter().map(| f | f.1.iter()
+                .map(| n | Vec3::from_array( to_quake( world, *n, 0.0 ) ).normalize().to_array() ).collect() ).collect(),
+            uvs,
+            triangles,
+        } )
+    }
+
+    fn collect_node( &self, node : usize, parent : &[[f32; 4]; 4], out : &mut Vec<MeshSurface> ) -> Result<(), ImportError>
+    {
+        let n = &self.json["nodes"][node];
+        let world = mat_mul( parent, &node_matrix( n ) );
+        if let Some( mesh ) = n["mesh"].as_u64() {
+            let mesh = &self.json["meshes"][mesh as usize];
+            let mesh_name = mesh["name"].as_str().or_else(|| n["name"].as_str() ).unwrap_or( "mesh" );
+            for ( i, prim ) in mesh["primitives"].as_array().map_or( &[][..], | p | &p[..] ).iter().enumerate() {
+                // Only triangle lists map onto MD3 surfaces
+                if prim["mode"].as_u64().unwrap_or( 4 ) != 4 {
+                    continue;
+                }
+                out.push( self.primitive_surface( prim, &world, format!( "{}_{}", mesh_name, i ) )? );
+            }
+        }
+        if let Some( children ) = n["children"].as_array() {
+            for c in children.iter().filter_map(| c | c.as_u64() ) {
+                self.collect_node( c as usize, &world, out )?;
+            }
+        }
+        Ok( () )
+    }
+
+    /// Converts the default scene's triangle meshes into a model called `name`.
+    /// Node transforms are baked in, Y-up is converted to Z-up and morph
+    /// targets become frames 1 and up.
+    pub fn to_model( &self, name : &str ) -> Result<Md3Model, ImportError>
+    {
+        let scene = self.json["scene"].as_u64().unwrap_or( 0 ) as usize;
+        let identity = Transform::identity().to_matrix();
+        let mut surfaces = vec![];
+        match self.json["scenes"][scene]["nodes"].as_array() {
+            Some( roots ) => for r in roots.iter().filter_map(| r | r.as_u64() ) {
+                self.collect_node( r as usize, &identity, &mut surfaces )?;
+            },
+            // No scene: every node that is nobody's child is a root
+            None => {
+                let nodes = self.json["nodes"].as_array().map_or( &[][..], | n | &n[..] );
+                let children : Vec<u64> = nodes.iter()
+                    .flat_map(| n | n["children"].as_array().map_or( &[][..], | c | &c[..] ).iter() )
+                    .filter_map(| c | c.as_u64() ).collect();
+                for n in ( 0 .. nodes.len() ).filter(| n | !children.contains( &( *n as u64 ) ) ) {
+                    self.collect_node( n, &identity, &mut surfaces )?;
+                }
+            }
+        }
+        build_model( name, surfaces )
+    }
+}
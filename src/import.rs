@@ -0,0 +1,277 @@
+// Shared back end of the OBJ and glTF importers: turns plain triangle
+// meshes into an `Md3Model`, quantizing positions to MD3_XYZ_SCALE, packing
+// normals into the lat/lng `i16` and splitting surfaces that exceed the
+// engine limits.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::mem;
+use math::Vec3;
+use md3::{Md3Frame, Md3Header, Md3Model, Md3Shader, Md3St, Md3Surface, Md3SurfaceData,
+          Md3SurfaceHeader, Md3Triangle, Md3XyzNormal, MD3_MAX_FRAMES, MD3_MAX_SURFACES,
+          MD3_MAX_TRIANGLES, MD3_MAX_VERTS, MD3_XYZ_SCALE};
+
+#[derive(Debug)]
+pub enum ImportError
+{
+    Io( io::Error ),
+    /// Malformed source file
+    Parse { line : usize, message : String },
+    /// Inconsistent source data with no line to point at
+    Invalid { message : String },
+    /// The result would not load in the engine
+    Limit { what : &'static str, count : usize, max : usize },
+    /// A position does not fit the fixed point range (about +/-512 units)
+    PositionOutOfRange { position : [f32; 3] },
+}
+
+impl fmt::Display for ImportError
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            ImportError::Io( ref e ) => write!( f, "I/O error: {}", e ),
+            ImportError::Parse { line, ref message } => write!( f, "line {}: {}", line, message ),
+            ImportError::Invalid { ref message } => write!( f, "{}", message ),
+            ImportError::Limit { what, count, max } =>
+                write!( f, "{} {} exceeds the MD3 limit of {}", count, what, max ),
+            ImportError::PositionOutOfRange { position } =>
+                write!( f, "position {:?} is outside the MD3 coordinate range", position ),
+        }
+    }
+}
+
+impl Error for ImportError
+{
+    fn source( &self ) -> Option<&(dyn Error + 'static)>
+    {
+        match *self {
+            ImportError::Io( ref e ) => Some( e ),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for ImportError
+{
+    fn from( e : io::Error ) -> ImportError
+    {
+        ImportError::Io( e )
+    }
+}
+
+/// A triangle mesh with a single material, as produced by the importers.
+pub struct MeshSurface
+{
+    pub name : String,
+    pub material : String,
+    /// One position per vertex for every frame
+    pub positions : Vec<Vec<[f32; 3]>>,
+    /// One normal per vertex for every frame
+    pub normals : Vec<Vec<[f32; 3]>>,
+    /// Texture coordinates in MD3 convention (origin top-left)
+    pub uvs : Vec<[f32; 2]>,
+    pub triangles : Vec<[u32; 3]>,
+}
+
+/// Quantizes a position, refusing anything the `i16` encoding cannot hold.
+fn quantize( p : [f32; 3] ) -> Result<[i16; 3], ImportError>
+{
+    let limit = ( i16::MAX as f32 + 1.0 ) * MD3_XYZ_SCALE;
+    if !p.iter().all(| c | *c > -limit && *c < limit ) {
+        return Err( ImportError::PositionOutOfRange { position: p } );
+    }
+    // Snap to the nearest grid point first; encode_xyz truncates like the id
+    // tools, which would lose a whole step to float noise from transforms
+    let snap = | c : f32 | ( c / MD3_XYZ_SCALE ).round() * MD3_XYZ_SCALE;
+    Ok( Md3XyzNormal::encode_xyz( [ snap( p[0] ), snap( p[1] ), snap( p[2] ) ] ) )
+}
+
+/// Checks that every frame, the texture coordinates and the triangles of
+/// `surf` agree on its vertex count.
+fn check_surface( surf : &MeshSurface ) -> Result<(), ImportError>
+{
+    let invalid = | message : String | Err( ImportError::Invalid { message: format!( "surface {}: {}", surf.name, message ) } );
+    let vertex_count = surf.positions.first().map_or( 0, | f | f.len() );
+    if surf.normals.len() != surf.positions.len() {
+        return invalid( format!( "{} frames of positions but {} of normals", surf.positions.len(), surf.normals.len() ) );
+    }
+    for ( f, ( pos, nrm ) ) in surf.positions.iter().zip( surf.normals.iter() ).enumerate() {
+        if pos.len() != vertex_count || nrm.len() != vertex_count {
+            return invalid( format!( "frame {} has {} positions and {} normals, expected {}",
+                                     f, pos.len(), nrm.len(), vertex_count ) );
+        }
+    }
+    if surf.uvs.len() != vertex_count {
+        return invalid( format!( "{} vertices but {} texture coordinates", vertex_count, surf.uvs.len() ) );
+    }
+    if let Some( &i ) = surf.triangles.iter().flat_map(| t | t.iter() ).find(| &&i | i as usize >= vertex_count ) {
+        return invalid( format!( "index {} outside its {} vertices", i, vertex_count ) );
+    }
+    Ok( () )
+}
+
+/// Splits a surface whose vertex or triangle count exceeds the engine
+/// limits into several surfaces that each fit.
+fn split_surface( surf : MeshSurface ) -> Vec<MeshSurface>
+{
+    if surf.positions.first().map_or( 0, | f | f.len() ) <= MD3_MAX_VERTS
+        && surf.triangles.len() <= MD3_MAX_TRIANGLES {
+        return vec![ surf ];
+    }
+
+    let mut parts = vec![];
+    let mut remap = vec![ u32::MAX; surf.uvs.len() ];
+    let mut used : Vec<u32> = vec![];
+    let mut tris : Vec<[u32; 3]> = vec![];
+    let mut flush = | used : &mut Vec<u32>, tris : &mut Vec<[u32; 3]>, remap : &mut Vec<u32> | {
+        let part = MeshSurface {
+            name: format!( "{}_{}", surf.name, parts.len() ),
+            material: surf.material.clone(),
+            positions: surf.positions.iter().map(| f | used.iter().map(| &i | f[i as usize] ).collect() ).collect(),
+            normals: surf.normals.iter().map(| f | used.iter().map(| &i | f[i as usize] ).collect() ).collect(),
+            uvs: used.iter().map(| &i | surf.uvs[i as usize] ).collect(),
+            triangles: mem::take( tris ),
+        };
+        for &i in used.iter() {
+            remap[i as usize] = u32::MAX;
+        }
+        used.clear();
+        parts.push( part );
+    };
+
+    for tri in surf.triangles.iter() {
+        let new_verts = tri.iter().filter(| &&i | remap[i as usize] == u32::MAX ).count();
+        if used.len() + new_verts > MD3_MAX_VERTS || tris.len() + 1 > MD3_MAX_TRIANGLES {
+            flush( &mut used, &mut tris, &mut remap );
+        }
+        let mut t = [0u32; 3];
+        for k in 0 .. 3 {
+            let i = tri[k] as usize;
+            if remap[i] == u32::MAX {
+                remap[i] = used.len() as u32;
+                used.push( i as u32 );
+            }
+            t[k] = remap[i];
+        }
+        tris.push( t );
+    }
+    if !tris.is_empty() {
+        flush( &mut used, &mut tris, &mut remap );
+    }
+    parts
+}
+
+fn qpath<const N : usize>( name : &str ) -> [u8; N]
+{
+    let mut buf = [0u8; N];
+    let n = name.len().min( N - 1 );
+    buf[..n].copy_from_slice( &name.as_bytes()[..n] );
+    buf
+}
+
+/// Assembles an `Md3Model` called `name` from imported surfaces. Surfaces
+/// must either have the same number of frames or a single, static frame,
+/// and every frame, the UVs and the triangles of a surface must agree on its
+/// vertex count.
+pub fn build_model( name : &str, surfaces : Vec<MeshSurface> ) -> Result<Md3Model, ImportError>
+{
+    for surf in surfaces.iter() {
+        check_surface( surf )?;
+    }
+    let surfaces : Vec<MeshSurface> = surfaces.into_iter().flat_map( split_surface ).collect();
+    if surfaces.len() > MD3_MAX_SURFACES {
+        return Err( ImportError::Limit { what: "surfaces", count: surfaces.len(), max: MD3_MAX_SURFACES } );
+    }
+    let frame_count = surfaces.iter().map(| s | s.positions.len() ).max().unwrap_or( 1 ).max( 1 );
+    if frame_count > MD3_MAX_FRAMES {
+        return Err( ImportError::Limit { what: "frames", count: frame_count, max: MD3_MAX_FRAMES } );
+    }
+
+    let mut frames = vec![];
+    for f in 0 .. frame_count {
+        let mut mins = Vec3::new( 0.0, 0.0, 0.0 );
+        let mut maxs = Vec3::new( 0.0, 0.0, 0.0 );
+        let mut radius : f32 = 0.0;
+        let mut first = true;
+        for p in surfaces.iter().filter_map(| s | s.positions.get( f ) ).flat_map(| f | f.iter() ) {
+            let v = Vec3::from_array( *p );
+            if first {
+                mins = v;
+                maxs = v;
+                first = false;
+            }
+            mins = Vec3::new( mins.x.min( v.x ), mins.y.min( v.y ), mins.z.min( v.z ) );
+            maxs = Vec3::new( maxs.x.max( v.x ), maxs.y.max( v.y ), maxs.z.max( v.z ) );
+            radius = radius.max( v.length() );
+        }
+        frames.push( Md3Frame {
+            bounds: [ mins, maxs ],
+            local_origin: Vec3::new( 0.0, 0.0, 0.0 ),
+            radius,
+            name: qpath( &format!( "frame{}", f ) ),
+        } );
+    }
+
+    let mut md3_surfaces = vec![];
+    for mut surf in surfaces.into_iter() {
+        // Static surfaces of an animated model hold still in every frame
+        if surf.positions.len() == 1 && frame_count > 1 {
+            surf.positions = vec![ surf.positions[0].clone(); frame_count ];
+            surf.normals = vec![ surf.normals[0].clone(); frame_count ];
+        }
+        if surf.positions.len() != frame_count {
+            return Err( ImportError::Invalid {
+                message: format!( "surface {} has {} frames, expected {}", surf.name, surf.positions.len(), frame_count )
+            } );
+        }
+        let vertex_count = surf.positions[0].len();
+        let mut xyz_normals = Vec::with_capacity( vertex_count * frame_count );
+        for ( pos, nrm ) in surf.positions.iter().zip( surf.normals.iter() ) {
+            for ( p, n ) in pos.iter().zip( nrm.iter() ) {
+                xyz_normals.push( Md3XyzNormal { xyz: quantize( *p )?, normal: Md3XyzNormal::encode_normal( *n ) } );
+            }
+        }
+        md3_surfaces.push( Md3Surface {
+            header: Md3SurfaceHeader {
+                ident: i32::from_le_bytes( *b"IDP3" ),
+                name: qpath( &surf.name ),
+                flags: 0,
+                frame_count: frame_count as i32,
+                shader_count: 1,
+                vertex_count: vertex_count as i32,
+                triangle_count: surf.triangles.len() as i32,
+                // Recomputed by write_to
+                triangles_offset: 0, shaders_offset: 0, st_offset: 0, xyz_normals_offset: 0, end_offset: 0,
+            },
+            data: Md3SurfaceData {
+                triangles: surf.triangles.iter()
+                    .map(| t | Md3Triangle { indices: [ t[0] as i32, t[1] as i32, t[2] as i32 ] } ).collect(),
+                shaders: vec![ Md3Shader { name: qpath( &surf.material ), shader_index: 0 } ],
+                st_data: surf.uvs.iter().map(| uv | Md3St { st: *uv } ).collect(),
+                xyz_normals,
+            }
+        } );
+    }
+
+    Ok( Md3Model {
+        header: Md3Header {
+            ident: i32::from_le_bytes( *b"IDP3" ),
+            version: 15,
+            name: name.as_bytes().to_vec(),
+            flags: 0,
+            frame_count: frame_count as i32,
+            tags_count: 0,
+            surface_count: md3_surfaces.len() as i32,
+            skin_count: 0,
+            frames_offset: 0, tags_offset: 0, surfaces_offset: 0, eof_offset: 0,
+        },
+        frames,
+        surfaces: md3_surfaces,
+        tags: vec![],
+        st_buffer: vec![],
+        xyz_normals: vec![],
+        shaders: vec![],
+    } )
+}
@@ -7,12 +7,31 @@ Strings are null terminated in md3 and names are maximum
 
 extern crate byteorder;
 extern crate itertools;
+#[macro_use]
+extern crate serde_json;
+extern crate zip;
 
 // FIXME:  Implement loading for other structures such as 
 
+pub mod animation;
+pub mod geom;
+pub mod gltf;
+pub mod import;
+pub mod obj;
+pub mod pk3;
+pub mod player;
+pub mod scene;
+pub mod shader;
+pub mod skin;
+pub mod texture;
+pub mod validate;
+pub mod vfs;
+mod script;
+
 pub mod math
 {
 
+    #[derive(Copy, Clone, Debug, PartialEq)]
     pub struct Vec3
     {
         pub x : f32,
@@ -20,171 +39,172 @@ pub mod math
         pub z : f32
     }
 
-}
+    impl Vec3
+    {
+        pub fn new( x : f32, y : f32, z : f32 ) -> Vec3
+        {
+            Vec3 { x, y, z }
+        }
 
-#[macro_export]
-macro_rules! implement_geometry_types {
+        pub fn from_array( v : [f32; 3] ) -> Vec3
+        {
+            Vec3 { x: v[0], y: v[1], z: v[2] }
+        }
 
-    () => {
-        pub mod geom
+        pub fn to_array( self ) -> [f32; 3]
         {
-            extern crate md3_rs;
-            // use md3::Md3Model;
+            [ self.x, self.y, self.z ]
+        }
 
-            #[allow(dead_code)]
-            #[derive(Copy, Clone)]
-            pub struct GLVertex
-            {
-                pub position: [f32; 3],
-                pub normal:   [f32; 3],
-                pub uv:       [f32; 2]
-            }
+        pub fn dot( self, o : Vec3 ) -> f32
+        {
+            self.x * o.x + self.y * o.y + self.z * o.z
+        }
 
-            #[allow(dead_code)]
-            pub struct GLStaticMesh
-            {
-                pub indices: Vec<u16>,
-                pub vertices: Vec<GLVertex>
-            }
+        pub fn cross( self, o : Vec3 ) -> Vec3
+        {
+            Vec3::new( self.y * o.z - self.z * o.y, self.z * o.x - self.x * o.z, self.x * o.y - self.y * o.x )
+        }
 
-            #[allow(dead_code)]
-            pub struct GLVertexAnimatedMesh
-            {
-                pub frames : Vec<GLStaticMesh>
-            }
+        pub fn length( self ) -> f32
+        {
+            self.dot( self ).sqrt()
+        }
 
-            #[allow(dead_code)]
-            pub enum GLReadyMesh{
-                VertexAnimated( GLVertexAnimatedMesh ),
-                Static( GLStaticMesh ),
-                Corrupted( String ) // Failed on loading
+        /// Returns the unit vector in the same direction, or the vector itself if it is zero.
+        pub fn normalize( self ) -> Vec3
+        {
+            let len = self.length();
+            if len == 0.0 {
+                return self;
             }
+            Vec3::new( self.x / len, self.y / len, self.z / len )
+        }
 
-            pub struct UV{
-                pub u: f32, pub v: f32
-            }
+        /// Linear interpolation, `t == 0` gives `self` and `t == 1` gives `o`.
+        pub fn lerp( self, o : Vec3, t : f32 ) -> Vec3
+        {
+            Vec3::from_array( lerp3( self.to_array(), o.to_array(), t ) )
+        }
+    }
 
-            pub trait CreateGLReadyMesh {
-                fn create_gl_ready_mesh( &mut self ) -> GLReadyMesh;
-            }
+    impl ::std::ops::Add for Vec3
+    {
+        type Output = Vec3;
 
-            impl GLStaticMesh
-            {
-                pub fn empty() -> GLStaticMesh
-                {
-                    GLStaticMesh{
-                        indices: vec![], vertices: vec![]
-                    }
-                }
+        fn add( self, o : Vec3 ) -> Vec3
+        {
+            Vec3::new( self.x + o.x, self.y + o.y, self.z + o.z )
+        }
+    }
 
-            }
+    impl ::std::ops::Sub for Vec3
+    {
+        type Output = Vec3;
 
-            impl GLVertex
-            {
-                pub fn null() -> GLVertex
-                {
-                    GLVertex {
-                        position: [0 as f32; 3],
-                        normal:   [0 as f32; 3],
-                        uv:       [0 as f32; 2]
-                    }
-                }
-            }
+        fn sub( self, o : Vec3 ) -> Vec3
+        {
+            Vec3::new( self.x - o.x, self.y - o.y, self.z - o.z )
+        }
+    }
 
-            macro_rules! print_val {
-                ($val:expr) => {
-                    println!("{} = {}", stringify!($val), $val )
-                };
+    impl ::std::ops::Mul<f32> for Vec3
+    {
+        type Output = Vec3;
+
+        fn mul( self, s : f32 ) -> Vec3
+        {
+            Vec3::new( self.x * s, self.y * s, self.z * s )
+        }
+    }
+
+    /// Rigid placement in id's convention: an origin plus forward/left/up axis
+    /// rows, as stored in `Md3Tag`.
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub struct Transform
+    {
+        pub origin : Vec3,
+        pub axis : [Vec3; 3]
+    }
+
+    impl Transform
+    {
+        pub fn identity() -> Transform
+        {
+            Transform {
+                origin: Vec3::new( 0.0, 0.0, 0.0 ),
+                axis: [
+                    Vec3::new( 1.0, 0.0, 0.0 ),
+                    Vec3::new( 0.0, 1.0, 0.0 ),
+                    Vec3::new( 0.0, 0.0, 1.0 )
+                ]
             }
+        }
 
-            impl CreateGLReadyMesh for md3_rs::md3::Md3Model
-            {
-                // FINISHME
-                fn create_gl_ready_mesh( &mut self ) -> GLReadyMesh
-                {
-                    // Convert to GL ready format
-                    if self.header.frame_count > 1 {
-                        // VertexAnimated mesh will be produced
-                        let mut amesh = GLVertexAnimatedMesh { frames: vec![] };
-                        for surf in self.surfaces.iter() {
-                            let mut smesh = GLStaticMesh::empty();
-
-                            // Collect vertices
-                            print_val!( surf.data.xyz_normals.len() );
-
-                            // smesh.vertices = surf.data.xyz_normals
-                            //     .iter().map(| xyzn | { 
-                            //         GLVertex{
-                            //             position: xyzn.decode_xyz(),
-                            //             normal:    xyzn.decode_normal(),
-                            //             uv:       [ 0f32, 0f32 ]
-                            //         }
-                            //     }).collect();
-
-                            smesh.vertices = surf.data.xyz_normals.iter()
-                                .zip( surf.data.st_data.iter() )
-                                .map(|(xyzn, st)|{
-                                    GLVertex{
-                                        position:  xyzn.decode_xyz(),
-                                        normal:    xyzn.decode_normal(),
-                                        uv:        [ st.st[0]  ,  1.0f32 - st.st[1] - st.st[1].floor()]
-                                    }
-                                }).collect();
-
-                            print_val!( smesh.vertices.len() );
-                            // Collect indices 
-                            smesh.indices.reserve_exact( surf.data.triangles.len() * 3 );
-                            for tri in surf.data.triangles.iter() {
-                                smesh.indices.push( tri.indices[0] as u16 );
-                                smesh.indices.push( tri.indices[1] as u16 );
-                                smesh.indices.push( tri.indices[2] as u16 );
-                            }
-
-                            // push another frame
-                            amesh.frames.push( smesh);
-                        }
-                        return GLReadyMesh::VertexAnimated( amesh );
-                    }else if self.header.frame_count == 1{
-                        // Static mesh will be produced 
-
-                        let surf = &self.surfaces[0];
-
-                        let mut smesh = GLStaticMesh::empty();
-
-                        // Collect vertices
-                        print_val!( surf.data.xyz_normals.len() );
-                        print_val!( surf.data.st_data.len() );
-
-
-                        smesh.vertices = surf.data.xyz_normals.iter()
-                            .zip( surf.data.st_data.iter() )
-                            .map( |(xyzn, st)|{
-                                GLVertex{
-                                    position: xyzn.decode_xyz(),
-                                    normal:    xyzn.decode_normal(),
-                                    uv:        [ st.st[0]  ,  1.0f32 - st.st[1] - st.st[1].floor()]
-                                }
-                            }).collect();
-
-
-                        print_val!( smesh.vertices.len() );
-
-                        // Collect indices 
-                        smesh.indices.reserve_exact( surf.data.triangles.len() * 3 );
-                        for tri in surf.data.triangles.iter() {
-                            smesh.indices.push( tri.indices[0] as u16 );
-                            smesh.indices.push( tri.indices[1] as u16 );
-                            smesh.indices.push( tri.indices[2] as u16 );
-                        }
-
-                        return GLReadyMesh::Static( smesh );
-                    }
-                    return GLReadyMesh::Corrupted(String::from("Cannot make GL ready mesh from MD3 model!"));
-                }
+        /// Expresses a vector given in this transform's local axes in the parent space.
+        pub fn rotate( &self, v : Vec3 ) -> Vec3
+        {
+            self.axis[0] * v.x + self.axis[1] * v.y + self.axis[2] * v.z
+        }
+
+        pub fn transform_point( &self, p : Vec3 ) -> Vec3
+        {
+            self.origin + self.rotate( p )
+        }
+
+        /// Places `local`, expressed relative to this transform, in this
+        /// transform's parent space (CG_PositionEntityOnTag).
+        pub fn compose( &self, local : &Transform ) -> Transform
+        {
+            Transform {
+                origin: self.transform_point( local.origin ),
+                axis: [
+                    self.rotate( local.axis[0] ),
+                    self.rotate( local.axis[1] ),
+                    self.rotate( local.axis[2] )
+                ]
             }
+        }
 
+        /// Column-major 4x4 matrix (OpenGL / glTF layout): `m[column][row]`.
+        pub fn to_matrix( &self ) -> [[f32; 4]; 4]
+        {
+            let a = &self.axis;
+            let o = &self.origin;
+            [
+                [ a[0].x, a[0].y, a[0].z, 0.0 ],
+                [ a[1].x, a[1].y, a[1].z, 0.0 ],
+                [ a[2].x, a[2].y, a[2].z, 0.0 ],
+                [ o.x, o.y, o.z, 1.0 ]
+            ]
+        }
+    }
 
+    pub fn lerp3( a : [f32; 3], b : [f32; 3], t : f32 ) -> [f32; 3]
+    {
+        [
+            a[0] + ( b[0] - a[0] ) * t,
+            a[1] + ( b[1] - a[1] ) * t,
+            a[2] + ( b[2] - a[2] ) * t
+        ]
+    }
 
+    pub fn normalize3( v : [f32; 3] ) -> [f32; 3]
+    {
+        Vec3::from_array( v ).normalize().to_array()
+    }
+
+}
+
+/// Compatibility shim for crates that used to inject the GL mesh types with
+/// `implement_geometry_types!()`; it now just re-exports `md3_rs::geom`.
+#[macro_export]
+macro_rules! implement_geometry_types {
+
+    () => {
+        pub mod geom
+        {
+            pub use $crate::geom::*;
         }
     };
 }
@@ -195,13 +215,118 @@ pub mod md3 {
     use std::io::prelude::*;
     // use std::ffi::CString;
     use std::io::SeekFrom;
+    use std::io;
     use std::fs::File;
+    use std::fmt;
+    use std::error::Error;
     use std::mem;
-    use byteorder::{LittleEndian,ReadBytesExt};
-    use math::Vec3;
+    use std::sync::OnceLock;
+    use byteorder::{LittleEndian,ReadBytesExt,WriteBytesExt};
+    use math::{Vec3, Transform, lerp3, normalize3};
+
+    pub const MAX_QPATH : usize = 64;
+    pub const MD3_XYZ_SCALE : f32 = 1.0/64.0;
+    pub const MD3_VERSION : i32 = 15;
+
+    // Engine limits (qfiles.h)
+    pub const MD3_MAX_FRAMES    : usize = 1024;
+    pub const MD3_MAX_TAGS      : usize = 16;
+    pub const MD3_MAX_SURFACES  : usize = 32;
+    pub const MD3_MAX_SHADERS   : usize = 256;
+    pub const MD3_MAX_VERTS     : usize = 4096;
+    pub const MD3_MAX_TRIANGLES : usize = 8192;
+    pub const MD3_IDENT : [u8; 4] = *b"IDP3";
+
+    // On-disk sizes of the fixed-size MD3 structures
+    const MD3_FRAME_SIZE    : u64 = 56;
+    const MD3_TAG_SIZE      : u64 = 112;
+    const MD3_SHADER_SIZE   : u64 = 68;
+    const MD3_TRIANGLE_SIZE : u64 = 12;
+    const MD3_ST_SIZE       : u64 = 8;
+    const MD3_XYZN_SIZE     : u64 = 8;
+    const MD3_HEADER_SIZE   : u64 = 108;
+    const MD3_SURFACE_HEADER_SIZE : u64 = 108;
+
+    /// Error produced while loading an MD3 model.
+    ///
+    /// Every variant carries the name of the structure being read and the
+    /// byte offset (from the start of the stream) at which the problem was hit.
+    #[derive(Debug)]
+    pub enum Md3Error
+    {
+        /// Underlying I/O failure other than running out of data
+        Io { structure : &'static str, offset : u64, source : io::Error },
+        /// The stream does not start with the IDP3 magic
+        BadMagic { offset : u64, found : [u8; 4] },
+        /// The header declares a version other than 15
+        UnsupportedVersion { offset : u64, version : i32 },
+        /// An offset field points before the start or past the end of the stream
+        OffsetOutOfRange { structure : &'static str, offset : u64, value : i64 },
+        /// A count field is negative or too large to fit in the stream
+        CountOverflow { structure : &'static str, offset : u64, count : i64 },
+        /// The stream ended in the middle of a lump
+        TruncatedLump { structure : &'static str, offset : u64 },
+        /// Two counts that must agree do not, e.g. ST coordinates vs. vertex_count
+        CountMismatch { structure : &'static str, offset : u64, expected : i64, found : i64 },
+    }
+
+    impl fmt::Display for Md3Error
+    {
+        fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+        {
+            match *self {
+                Md3Error::Io { structure, offset, ref source } =>
+                    write!( f, "I/O error reading {} at offset {}: {}", structure, offset, source ),
+                Md3Error::BadMagic { offset, found } =>
+                    write!( f, "bad MD3 magic {:?} at offset {}", found, offset ),
+                Md3Error::UnsupportedVersion { offset, version } =>
+                    write!( f, "unsupported MD3 version {} at offset {}", version, offset ),
+                Md3Error::OffsetOutOfRange { structure, offset, value } =>
+                    write!( f, "{} offset {} out of range (field at offset {})", structure, value, offset ),
+                Md3Error::CountOverflow { structure, offset, count } =>
+                    write!( f, "{} count {} overflows stream (field at offset {})", structure, count, offset ),
+                Md3Error::TruncatedLump { structure, offset } =>
+                    write!( f, "truncated {} lump at offset {}", structure, offset ),
+                Md3Error::CountMismatch { structure, offset, expected, found } =>
+                    write!( f, "{} count mismatch at offset {}: expected {}, found {}",
+                            structure, offset, expected, found ),
+            }
+        }
+    }
+
+    impl Error for Md3Error
+    {
+        fn source( &self ) -> Option<&(dyn Error + 'static)>
+        {
+            match *self {
+                Md3Error::Io { ref source, .. } => Some( source ),
+                _ => None
+            }
+        }
+    }
+
+    /// Builds a `map_err` adapter tagging an I/O error with the structure and offset being read.
+    fn lump_err( structure : &'static str, offset : u64 ) -> impl Fn( io::Error ) -> Md3Error
+    {
+        move | e : io::Error | {
+            if e.kind() == io::ErrorKind::UnexpectedEof {
+                Md3Error::TruncatedLump { structure, offset }
+            } else {
+                Md3Error::Io { structure, offset, source: e }
+            }
+        }
+    }
 
-    const MAX_QPATH : usize = 64;
-    const MD3_XYZ_SCALE : f32 = 1.0/64.0;
+    /// How `Md3Model::from_reader_with_mode` locates the MD3 header.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum LoadMode
+    {
+        /// The header must sit at offset 0 of the stream
+        Strict,
+        /// Skip any junk prefix by searching for the first "IDP3" followed by
+        /// version 15; offsets are then relative to where the magic was found
+        Embedded,
+    }
 
     #[allow(dead_code)]
     pub struct Md3Header
@@ -262,7 +387,7 @@ pub mod md3 {
         pub triangles:   Vec<Md3Triangle>,
         pub shaders:     Vec<Md3Shader>,
         pub st_data:     Vec<Md3St>,
-        pub xyz_normals: Vec<Md3XyzNormal>,
+        pub xyz_normals: Vec<Md3XyzNormal>, // frame_count * vertex_count, grouped by frame
     }
 
     pub struct Md3Surface
@@ -293,6 +418,7 @@ pub mod md3 {
         pub header : Md3Header,
         pub frames : Vec<Md3Frame>,
         pub surfaces : Vec<Md3Surface>,
+        pub tags : Vec<Md3Tag>, // frame_count * tags_count, grouped by frame
         pub st_buffer : Vec<Md3St>,
         pub xyz_normals : Vec<Md3XyzNormal>,
         pub shaders : Vec<Md3Shader>
@@ -315,61 +441,125 @@ pub mod md3 {
 
     // Some macros
 
-    macro_rules! assign_fields {
-        ($val:expr, $struct:ident; $($field:ident)+) => {
-            $($struct.$field = $val;)+ 
-        }
-    }
-
-
     macro_rules! read_all_little_i32{
-        ($i:ident;$($var_name:expr),+) => {
-            $(($var_name = $i.read_i32::<LittleEndian>().expect(format!("FAILED LOADING FIELD OF {} STRUCURE", stringify!($s) ).as_str()));)+;
-
-        }; 
-        (($s:ident, $i:ident);$($var_name:tt),+) => {
-            $(($s.$var_name = $i.read_i32::<LittleEndian>().expect(format!("FAILED LOADING FIELD OF {} STRUCURE", stringify!($s) ).as_str()));)+;
+        ($i:ident, $e:expr;$($var_name:expr),+) => {
+            $($var_name = $i.read_i32::<LittleEndian>().map_err( $e )?;)+
+        };
+        (($s:ident, $i:ident, $e:expr);$($var_name:tt),+) => {
+            $($s.$var_name = $i.read_i32::<LittleEndian>().map_err( $e )?;)+
         };
     }
 
     macro_rules! read_all_little_i16{
-        ($i:ident;$($var_name:expr),+) => {
-            $(($var_name = $i.read_i16::<LittleEndian>().expect(format!("FAILED LOADING FIELD OF {} STRUCURE", stringify!($s) ).as_str()));)+;
-
-        }; 
-        (($s:ident, $i:ident);$($var_name:tt),+) => {
-            $(($s.$var_name = $i.read_i16::<LittleEndian>().expect(format!("FAILED LOADING FIELD OF {} STRUCURE", stringify!($s) ).as_str()));)+;
+        ($i:ident, $e:expr;$($var_name:expr),+) => {
+            $($var_name = $i.read_i16::<LittleEndian>().map_err( $e )?;)+
+        };
+        (($s:ident, $i:ident, $e:expr);$($var_name:tt),+) => {
+            $($s.$var_name = $i.read_i16::<LittleEndian>().map_err( $e )?;)+
         };
     }
 
     macro_rules! read_all_little_f32{
-        ($i:ident;$($var_name:expr),+) => {
-            $(($var_name = $i.read_f32::<LittleEndian>().expect(format!("FAILED LOADING FIELD OF {} STRUCURE", stringify!($s) ).as_str()));)+;
-
-        }; 
-        (($s:ident, $i:ident);$($var_name:tt),+) => {
-            $(($s.$var_name = $i.read_f32::<LittleEndian>().expect(format!("FAILED LOADING FIELD OF {} STRUCURE", stringify!($s) ).as_str()));)+;
+        ($i:ident, $e:expr;$($var_name:expr),+) => {
+            $($var_name = $i.read_f32::<LittleEndian>().map_err( $e )?;)+
+        };
+        (($s:ident, $i:ident, $e:expr);$($var_name:tt),+) => {
+            $($s.$var_name = $i.read_f32::<LittleEndian>().map_err( $e )?;)+
         };
     }
 
+    /// Checks that `count` elements of `elem_size` bytes starting at `start`
+    /// can fit in a stream of `stream_len` bytes.
+    fn check_lump( structure : &'static str, start : u64, count : i32,
+                   elem_size : u64, stream_len : u64 ) -> Result<(), Md3Error>
+    {
+        if count < 0 {
+            return Err( Md3Error::CountOverflow { structure, offset: start, count: count as i64 } );
+        }
+        if start > stream_len {
+            return Err( Md3Error::OffsetOutOfRange { structure, offset: start, value: start as i64 } );
+        }
+        match (count as u64).checked_mul( elem_size ) {
+            Some( size ) if size <= stream_len - start => Ok( () ),
+            _ => Err( Md3Error::CountOverflow { structure, offset: start, count: count as i64 } )
+        }
+    }
+
+    /// Resolves an `i32` offset field relative to `base`, failing if it lands outside the stream.
+    fn resolve_offset( structure : &'static str, base : u64, value : i32,
+                       stream_len : u64 ) -> Result<u64, Md3Error>
+    {
+        let abs = base as i64 + value as i64;
+        if abs < 0 || abs as u64 > stream_len {
+            return Err( Md3Error::OffsetOutOfRange { structure, offset: base, value: value as i64 } );
+        }
+        Ok( abs as u64 )
+    }
+
+    /// Scans the stream for the first IDP3 magic followed by a supported version
+    /// and returns its offset.
+    fn find_magic<R : Read + Seek>( inp : &mut R ) -> Result<u64, Md3Error>
+    {
+        seek_to( inp, "Md3Header", 0 )?;
+        let mut window = [0u8; 4];
+        let mut first = [0u8; 4];
+        let mut pos : u64 = 0;
+        let mut b = [0u8; 1];
+        loop {
+            if let Err( e ) = inp.read_exact( &mut b ) {
+                if e.kind() == io::ErrorKind::UnexpectedEof {
+                    return Err( Md3Error::BadMagic { offset: 0, found: first } );
+                }
+                return Err( lump_err( "Md3Header", pos )( e ) );
+            }
+            if pos < 4 {
+                first[pos as usize] = b[0];
+            }
+            window = [ window[1], window[2], window[3], b[0] ];
+            pos += 1;
+            if pos >= 4 && window == MD3_IDENT {
+                match inp.read_i32::<LittleEndian>() {
+                    Ok( MD3_VERSION ) => return Ok( pos - 4 ),
+                    _ => seek_to( inp, "Md3Header", pos )?
+                }
+            }
+        }
+    }
 
+    fn seek_to<R : Seek>( inp : &mut R, structure : &'static str, offset : u64 ) -> Result<(), Md3Error>
+    {
+        inp.seek( SeekFrom::Start( offset ) ).map_err( lump_err( structure, offset ) )?;
+        Ok( () )
+    }
 
 
     impl Md3Header
     {
 
-        fn read_from<R : Read + Seek>( inp: &mut R ) -> Md3Header
+        fn read_from<R : Read + Seek>( inp: &mut R, start_offset : u64 ) -> Result<Md3Header, Md3Error>
         {
-            let mut hdr : Md3Header = unsafe { mem::zeroed() };
-            hdr.name  =  vec![0 as u8;64];
+            let mut hdr = Md3Header {
+                ident: 0, version: 0, name: vec![0u8; MAX_QPATH],
+                flags: 0, frame_count: 0, tags_count: 0, surface_count: 0, skin_count: 0,
+                frames_offset: 0, tags_offset: 0, surfaces_offset: 0, eof_offset: 0
+            };
             // load_raw_struct( &mut hdr, in_strm );
-            hdr.version = inp.read_i32::<LittleEndian>().unwrap();
-            inp.read( &mut hdr.name ).unwrap();
+            let mut ident = [0u8; 4];
+            inp.read_exact( &mut ident ).map_err( lump_err( "Md3Header", start_offset ) )?;
+            if ident != MD3_IDENT {
+                return Err( Md3Error::BadMagic { offset: start_offset, found: ident } );
+            }
+            hdr.ident = i32::from_le_bytes( ident );
+            hdr.version = inp.read_i32::<LittleEndian>().map_err( lump_err( "Md3Header", start_offset ) )?;
+            if hdr.version != MD3_VERSION {
+                return Err( Md3Error::UnsupportedVersion { offset: start_offset, version: hdr.version } );
+            }
+            inp.read_exact( &mut hdr.name ).map_err( lump_err( "Md3Header", start_offset ) )?;
             let hdr_name_nul =  hdr.name.iter().skip_while(|&&x| x == 0).count(); 
             hdr.name.truncate( hdr_name_nul );
 
             read_all_little_i32!{
-                (hdr, inp);
+                (hdr, inp, lump_err( "Md3Header", start_offset ));
                 flags, frame_count,
                 tags_count, surface_count,
                 skin_count, frames_offset,
@@ -377,75 +567,116 @@ pub mod md3 {
                 eof_offset
             };
 
-            return hdr;
+            Ok( hdr )
         }
     }
     impl Vec3
     {
-        fn read_from<RType: Read >( &mut self , inp : &mut RType )
+        fn read_from<RType: Read >( &mut self , inp : &mut RType,
+                                    structure : &'static str, offset : u64 ) -> Result<(), Md3Error>
         {
-            assign_fields!{
-                inp.read_f32::<LittleEndian>()
-                    .expect(String::from("Could not read VEC3 from stream!").as_str()), self ;
-                x y z
-            }
+            read_all_little_f32!{
+                (self, inp, lump_err( structure, offset ));
+                x, y, z
+            };
+            Ok( () )
         }
     }
 
     impl Md3St
     {
         fn read_from<RType: Read + Seek>( inp : &mut RType, start_offset : u64,
-                                          buff : &mut Vec<Md3St>, count : i32 )
+                                          buff : &mut Vec<Md3St>, count : i32 ) -> Result<(), Md3Error>
         {
-            inp.seek( SeekFrom::Start( start_offset ) )
-                .expect("Error while seeking to ST position in MD3 file!");
-            for _  in 0 ..  count {
+            seek_to( inp, "Md3St", start_offset )?;
+            for i in 0 .. count as u64 {
                 let mut st : Md3St = unsafe { mem::zeroed() };
                 read_all_little_f32!{
-                    inp;
+                    inp, lump_err( "Md3St", start_offset + i * MD3_ST_SIZE );
                     st.st[0], st.st[1]
-                }
+                };
 
                 buff.push( st );
             }
+            Ok( () )
         }
     }
 
     impl Md3Frame
     {
-        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64, buff : &mut Vec<Md3Frame>, count: i32 ) 
+        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64,
+                                          buff : &mut Vec<Md3Frame>, count: i32 ) -> Result<(), Md3Error>
         {
-            inp.seek( SeekFrom::Start( start_offset ) )
-                .expect("Failed to seek to position of MD3 frames within file!");
+            seek_to( inp, "Md3Frame", start_offset )?;
 
-            for _ in 0 .. count  {
+            for i in 0 .. count as u64 {
+                let offset = start_offset + i * MD3_FRAME_SIZE;
                 let mut frm : Md3Frame = unsafe { mem::zeroed() };
-                frm.bounds[0].read_from( inp );
-                frm.bounds[1].read_from( inp );
-                frm.local_origin.read_from( inp ); 
-                read_all_little_f32!( (frm,inp); radius );
-                inp.read(  &mut frm.name ).unwrap();
+                frm.bounds[0].read_from( inp, "Md3Frame", offset )?;
+                frm.bounds[1].read_from( inp, "Md3Frame", offset )?;
+                frm.local_origin.read_from( inp, "Md3Frame", offset )?;
+                read_all_little_f32!( (frm, inp, lump_err( "Md3Frame", offset )); radius );
+                inp.read_exact( &mut frm.name ).map_err( lump_err( "Md3Frame", offset ) )?;
                 buff.push( frm );
             }
+            Ok( () )
         }
     }
 
+    const FUNCTABLE_SIZE : usize = 1024;
+    const FUNCTABLE_MASK : usize = FUNCTABLE_SIZE - 1;
+
+    /// The renderer's tr.sinTable. Note the engine divides the turn by
+    /// FUNCTABLE_SIZE - 1, so entries are slightly off a true sine; we keep that
+    /// to decode normals bit-for-bit.
+    fn sin_table() -> &'static [f32]
+    {
+        use std::f64::consts::PI;
+        static TABLE : OnceLock<Vec<f32>> = OnceLock::new();
+        TABLE.get_or_init(|| {
+            ( 0 .. FUNCTABLE_SIZE ).map(| i | {
+                let deg = i as f32 * 360.0f32 / ( FUNCTABLE_SIZE - 1 ) as f32;
+                ( deg as f64 * PI / 180.0 ).sin() as f32
+            }).collect()
+        })
+    }
+
+    /// Decoded normal for each of the 65536 packed codes, indexed by the code as `u16`.
+    fn normal_table() -> &'static [[f32; 3]]
+    {
+        static TABLE : OnceLock<Vec<[f32; 3]>> = OnceLock::new();
+        TABLE.get_or_init(|| {
+            let sin = sin_table();
+            let step = FUNCTABLE_SIZE / 256;
+            ( 0 .. 65536usize ).map(| code | {
+                let lat = ( ( code >> 8 ) & 0xff ) * step;
+                let lng = ( code & 0xff ) * step;
+                [
+                    sin[( lat + FUNCTABLE_SIZE / 4 ) & FUNCTABLE_MASK] * sin[lng],
+                    sin[lat] * sin[lng],
+                    sin[( lng + FUNCTABLE_SIZE / 4 ) & FUNCTABLE_MASK]
+                ]
+            }).collect()
+        })
+    }
+
     impl Md3XyzNormal
     {
 
-        fn read_from<RType: Read + Seek>( inp : &mut RType, start_offset : u64, buff : &mut Vec<Md3XyzNormal>, count : i32 )
+        fn read_from<RType: Read + Seek>( inp : &mut RType, start_offset : u64,
+                                          buff : &mut Vec<Md3XyzNormal>, count : i32 ) -> Result<(), Md3Error>
         {
-            inp.seek( SeekFrom::Start( start_offset as u64 ) )
-                .expect("Error while seeking to XyzNormal position in MD3 file!");
+            seek_to( inp, "Md3XyzNormal", start_offset )?;
             // xyz is stored as i16, they have to be scaled by a factor of 1/64
-            for _ in 0 .. count {
+            for i in 0 .. count as u64 {
                 let mut xyzn : Md3XyzNormal  = unsafe { mem::zeroed() };
                 read_all_little_i16!{
-                    inp;
+                    inp, lump_err( "Md3XyzNormal", start_offset + i * MD3_XYZN_SIZE );
                     xyzn.xyz[0], xyzn.xyz[1], xyzn.xyz[2], xyzn.normal
                 };
                 buff.push( xyzn );
             }
+            Ok( () )
         }
 
         pub fn decode_xyz( &self ) -> [f32; 3]
@@ -457,116 +688,277 @@ pub mod md3 {
             ]
         }
 
+        /// Decodes the packed latitude (high byte) / longitude (low byte) normal
+        /// exactly like the renderer: X = cos(lat) sin(lng), Y = sin(lat) sin(lng),
+        /// Z = cos(lng), with both angles in 256ths of a turn looked up in the
+        /// engine's sine table.
         pub fn decode_normal( &self ) -> [f32; 3]
         {
-            use std::f64::consts::PI;
-            let lat = ((self.normal >> 8) & 255) as f64 * (2.0* PI) / 255.0;
-            let lng = (self.normal & 255) as f64 * ( 2.0 * PI ) / 255.0;
+            normal_table()[self.normal as u16 as usize]
+        }
+
+        /// Inverse of `decode_xyz`. Like the id tools this truncates towards
+        /// zero; values outside the `i16` range are clamped.
+        pub fn encode_xyz( xyz : [f32; 3] ) -> [i16; 3]
+        {
             [
-                (lat.cos() *  lng.sin()) as f32,
-                (lat.sin()  *  lng.sin()) as f32,
-                lng.cos() as f32
+                ( xyz[0] / MD3_XYZ_SCALE ) as i16,
+                ( xyz[1] / MD3_XYZ_SCALE ) as i16,
+                ( xyz[2] / MD3_XYZ_SCALE ) as i16
             ]
         }
+
+        /// Inverse of `decode_normal`, packing a unit normal into latitude (high
+        /// byte) and longitude (low byte) the way q3data's NormalToLatLong does,
+        /// truncating both angles. Straight up and down get fixed codes.
+        pub fn encode_normal( normal : [f32; 3] ) -> i16
+        {
+            if normal[0] == 0.0 && normal[1] == 0.0 {
+                return if normal[2] > 0.0 { 0 } else { 128 };
+            }
+            let lat = ( normal[1].atan2( normal[0] ).to_degrees() * ( 255.0 / 360.0 ) ) as i32 & 0xff;
+            let lng = ( normal[2].clamp( -1.0, 1.0 ).acos().to_degrees() * ( 255.0 / 360.0 ) ) as i32 & 0xff;
+            ( ( lat << 8 ) | lng ) as i16
+        }
+    }
+
+    impl Md3SurfaceHeader
+    {
+        pub fn name_str( &self ) -> &str
+        {
+            qpath_str( &self.name )
+        }
     }
 
     impl Md3Surface
     {
-        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64 , buff : &mut Vec<Md3Surface>, count: i32 ) 
+        /// Returns the `vertex_count` vertices of `frame`, or an empty slice if
+        /// the frame is out of range.
+        pub fn frame_vertices( &self, frame : usize ) -> &[Md3XyzNormal]
+        {
+            let n = self.header.vertex_count.max( 0 ) as usize;
+            // A huge `frame` must not wrap around into a valid range
+            frame.checked_mul( n )
+                .and_then(| start | start.checked_add( n ).map(| end | start .. end ) )
+                .and_then(| range | self.data.xyz_normals.get( range ) )
+                .unwrap_or( &[] )
+        }
+
+        /// Decoded vertex positions blended between two frames, `t` in 0..1.
+        /// Returns an empty vector if either frame is out of range.
+        pub fn interpolate( &self, frame_a : usize, frame_b : usize, t : f32 ) -> Vec<[f32; 3]>
+        {
+            let ( a, b ) = ( self.frame_vertices( frame_a ), self.frame_vertices( frame_b ) );
+            if a.len() != b.len() {
+                return vec![];
+            }
+            a.iter().zip( b.iter() )
+                .map(|( va, vb )| lerp3( va.decode_xyz(), vb.decode_xyz(), t ) )
+                .collect()
+        }
+
+        /// Decoded vertex normals blended between two frames and renormalized.
+        pub fn interpolate_normals( &self, frame_a : usize, frame_b : usize, t : f32 ) -> Vec<[f32; 3]>
         {
+            let ( a, b ) = ( self.frame_vertices( frame_a ), self.frame_vertices( frame_b ) );
+            if a.len() != b.len() {
+                return vec![];
+            }
+            a.iter().zip( b.iter() )
+                .map(|( va, vb )| normalize3( lerp3( va.decode_normal(), vb.decode_normal(), t ) ) )
+                .collect()
+        }
 
-            inp.seek( SeekFrom::Start( start_offset + 4 as u64) ) // + 4 because of IDENT 
-                .expect("Could not seek to surfaces offset!"); 
+        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64,
+                                          buff : &mut Vec<Md3Surface>, count: i32,
+                                          stream_len : u64 ) -> Result<(), Md3Error>
+        {
 
-            println!("READING SURFACES; COUNT: {}", count);
+            // Surfaces are laid out back to back; each header's lump offsets are
+            // relative to the start of that surface and end_offset points at the next one
+            let mut surf_start = start_offset;
 
             for _ in 0 .. count {
+                seek_to( inp, "Md3SurfaceHeader", surf_start )?;
+
                 let mut surf_header : Md3SurfaceHeader = unsafe { mem::zeroed() };
-                let mut surf_data   : Md3SurfaceData   = unsafe { mem::zeroed() };
+                let mut surf_data = Md3SurfaceData {
+                    triangles: vec![], shaders: vec![], st_data: vec![], xyz_normals: vec![]
+                };
 
-                inp.read( &mut surf_header.name ).unwrap();
+                read_all_little_i32!( (surf_header, inp, lump_err( "Md3SurfaceHeader", surf_start )); ident );
+                inp.read_exact( &mut surf_header.name ).map_err( lump_err( "Md3SurfaceHeader", surf_start ) )?;
                 read_all_little_i32!{
-                    (surf_header,inp);
+                    (surf_header, inp, lump_err( "Md3SurfaceHeader", surf_start ));
                     flags, frame_count, shader_count,
                     vertex_count, triangle_count, triangles_offset,
                     shaders_offset, st_offset, xyz_normals_offset,
                     end_offset
+                };
+
+                let triangles_start = resolve_offset( "Md3Triangle", surf_start,
+                                                      surf_header.triangles_offset, stream_len )?;
+                let shaders_start = resolve_offset( "Md3Shader", surf_start,
+                                                    surf_header.shaders_offset, stream_len )?;
+                let st_start = resolve_offset( "Md3St", surf_start,
+                                               surf_header.st_offset, stream_len )?;
+                let xyzn_start = resolve_offset( "Md3XyzNormal", surf_start,
+                                                 surf_header.xyz_normals_offset, stream_len )?;
+
+                check_lump( "Md3Triangle", triangles_start, surf_header.triangle_count,
+                            MD3_TRIANGLE_SIZE, stream_len )?;
+                // One full set of vertices is stored per frame
+                let total_verts = if surf_header.frame_count < 0 { None } else {
+                    surf_header.vertex_count.checked_mul( surf_header.frame_count )
+                };
+                let total_verts = match total_verts {
+                    Some( n ) => n,
+                    None => return Err( Md3Error::CountOverflow {
+                        structure: "Md3XyzNormal", offset: xyzn_start,
+                        count: surf_header.vertex_count as i64 * surf_header.frame_count as i64
+                    } )
+                };
+                check_lump( "Md3XyzNormal", xyzn_start, total_verts,
+                            MD3_XYZN_SIZE, stream_len )?;
+                check_lump( "Md3Shader", shaders_start, surf_header.shader_count,
+                            MD3_SHADER_SIZE, stream_len )?;
+                // A surface that does not advance would make us re-read it forever
+                if surf_header.end_offset <= 0 {
+                    return Err( Md3Error::OffsetOutOfRange { structure: "Md3Surface", offset: surf_start,
+                                                             value: surf_header.end_offset as i64 } );
+                }
+                let next_start = resolve_offset( "Md3Surface", surf_start,
+                                                 surf_header.end_offset, stream_len )?;
+
+                // Texture coordinates are per vertex and shared by every frame
+                check_lump( "Md3St", st_start, surf_header.vertex_count,
+                            MD3_ST_SIZE, stream_len )?;
+                // A short ST lump runs into the XYZ lump or past the surface
+                let st_end = st_start + surf_header.vertex_count as u64 * MD3_ST_SIZE;
+                let st_limit = if st_start < xyzn_start { xyzn_start.min( next_start ) } else { next_start };
+                if st_end > st_limit {
+                    return Err( Md3Error::CountMismatch {
+                        structure: "Md3St", offset: st_start,
+                        expected: ( st_limit.saturating_sub( st_start ) / MD3_ST_SIZE ) as i64,
+                        found: surf_header.vertex_count as i64
+                    } );
                 }
 
                 // FIXME: WE SHOULD LOAD Md3SurfaceData right now!
                 // FINISHME
-                Md3Triangle::read_from( inp, start_offset + (surf_header.triangles_offset as u64),
-                                        &mut surf_data.triangles, surf_header.triangle_count );
+                Md3Triangle::read_from( inp, triangles_start,
+                                        &mut surf_data.triangles, surf_header.triangle_count )?;
 
 
-                Md3XyzNormal::read_from( inp, start_offset+surf_header.xyz_normals_offset as u64,
+                Md3XyzNormal::read_from( inp, xyzn_start,
                                          &mut surf_data.xyz_normals,
-                                         surf_header.vertex_count );
-
-                Md3Shader::read_from( inp, start_offset+surf_header.shaders_offset as u64,
-                                      &mut surf_data.shaders, surf_header.shader_count );
+                                         total_verts )?;
 
-                Md3St::read_from( inp, start_offset+surf_header.st_offset as u64,
-                                  &mut surf_data.st_data, surf_header.triangle_count );
+                Md3Shader::read_from( inp, shaders_start,
+                                      &mut surf_data.shaders, surf_header.shader_count )?;
 
-                println!("LOADED {} OF XYZ_NORMALS", surf_data.xyz_normals.len());
+                Md3St::read_from( inp, st_start,
+                                  &mut surf_data.st_data, surf_header.vertex_count )?;
 
                 buff.push( Md3Surface{ header: surf_header, data: surf_data } );
+                surf_start = next_start;
             }
+            Ok( () )
         }
     }
 
     impl Md3Shader
     {
-        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64, buff : &mut Vec<Md3Shader>, count: i32 ) 
+        pub fn name_str( &self ) -> &str
         {
-            inp.seek( SeekFrom::Start( start_offset as u64 ) )
-                .expect("Error while seeking to Shader position in MD3 file");
-            for _ in 0 .. count {
+            qpath_str( &self.name )
+        }
+
+        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64,
+                                          buff : &mut Vec<Md3Shader>, count: i32 ) -> Result<(), Md3Error>
+        {
+            seek_to( inp, "Md3Shader", start_offset )?;
+            for i in 0 .. count as u64 {
+                let offset = start_offset + i * MD3_SHADER_SIZE;
                 let mut shdr : Md3Shader = unsafe { mem::zeroed() };
-                inp.read( &mut shdr.name )
-                    .expect("Could not read shader name within MD3 file!");
-                read_all_little_i32!( (shdr, inp); shader_index );
+                inp.read_exact( &mut shdr.name ).map_err( lump_err( "Md3Shader", offset ) )?;
+                read_all_little_i32!( (shdr, inp, lump_err( "Md3Shader", offset )); shader_index );
                 buff.push( shdr );
             }
+            Ok( () )
         }
     }
 
+    /// Returns the part of a fixed-size, NUL-padded MD3 name before the first NUL.
+    pub fn qpath_str( name : &[u8] ) -> &str
+    {
+        ::std::str::from_utf8( qpath_bytes( name ) ).unwrap_or("")
+    }
+
     impl Md3Tag
     {
-        fn read_from<RType: Read + Seek>( inp: &mut RType, buff : &mut Vec<Md3Tag>, count: i32 ) 
+        pub fn name_str( &self ) -> &str
         {
-            for _ in 0 .. count {
+            qpath_str( &self.name )
+        }
+
+        /// The tag as a transform relative to the model it belongs to.
+        pub fn transform( &self ) -> Transform
+        {
+            Transform { origin: self.origin, axis: self.axis }
+        }
+
+        /// Blends this tag towards `other` the way the engine does: the origin is
+        /// lerped and each axis is lerped then renormalized.
+        pub fn interpolate( &self, other : &Md3Tag, t : f32 ) -> Md3Tag
+        {
+            Md3Tag {
+                name: self.name,
+                origin: self.origin.lerp( other.origin, t ),
+                axis: [
+                    self.axis[0].lerp( other.axis[0], t ).normalize(),
+                    self.axis[1].lerp( other.axis[1], t ).normalize(),
+                    self.axis[2].lerp( other.axis[2], t ).normalize()
+                ]
+            }
+        }
+
+        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64,
+                                          buff : &mut Vec<Md3Tag>, count: i32 ) -> Result<(), Md3Error>
+        {
+            seek_to( inp, "Md3Tag", start_offset )?;
+            for i in 0 .. count as u64 {
+                let offset = start_offset + i * MD3_TAG_SIZE;
                 let mut tag  : Md3Tag = unsafe { mem::zeroed() };
-                inp.read( &mut tag.name ).unwrap();
-                tag.origin.read_from( inp );
-                tag.axis[0].read_from( inp );
-                tag.axis[1].read_from( inp );
-                tag.axis[2].read_from( inp );
+                inp.read_exact( &mut tag.name ).map_err( lump_err( "Md3Tag", offset ) )?;
+                tag.origin.read_from( inp, "Md3Tag", offset )?;
+                tag.axis[0].read_from( inp, "Md3Tag", offset )?;
+                tag.axis[1].read_from( inp, "Md3Tag", offset )?;
+                tag.axis[2].read_from( inp, "Md3Tag", offset )?;
                 buff.push( tag );
             }
+            Ok( () )
         }
     }
 
     impl Md3Triangle
     {
-        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64, buff : &mut Vec<Md3Triangle>, count: i32 ) 
+        fn read_from<RType: Read + Seek>( inp: &mut RType, start_offset : u64,
+                                          buff : &mut Vec<Md3Triangle>, count: i32 ) -> Result<(), Md3Error>
         {
-            inp.seek( SeekFrom::Start( start_offset as u64 ) ).
-                expect("Could not seek into file(Triangles)!");
+            seek_to( inp, "Md3Triangle", start_offset )?;
 
-            for _ in 0 .. count {
+            for i in 0 .. count as u64 {
                 let mut tri  : Md3Triangle = unsafe { mem::zeroed() };
-                {
-                    read_all_little_i32!(
-                        inp;
-                        tri.indices[0],
-                        tri.indices[1],
-                        tri.indices[2]
-                    );
-                }
+                read_all_little_i32!(
+                    inp, lump_err( "Md3Triangle", start_offset + i * MD3_TRIANGLE_SIZE );
+                    tri.indices[0],
+                    tri.indices[1],
+                    tri.indices[2]
+                );
                 buff.push( tri );
             }
+            Ok( () )
         }
     }
 
@@ -575,38 +967,76 @@ pub mod md3 {
     impl Md3Model
     {
 
-        pub fn load( fname : String ) -> Option<Md3Model>
+        pub fn name_str( &self ) -> &str
         {
-            use std::io::BufReader;
+            qpath_str( &self.header.name )
+        }
 
-            let _fh = File::open(fname).expect("Could not open MD3 model file!");
-            let mut fin = BufReader::new( _fh );
+        /// Returns the tags of `frame`, in file order.
+        pub fn frame_tags( &self, frame : usize ) -> &[Md3Tag]
+        {
+            let n = self.header.tags_count.max( 0 ) as usize;
+            // A huge `frame` must not wrap around into a valid range
+            frame.checked_mul( n )
+                .and_then(| start | start.checked_add( n ).map(| end | start .. end ) )
+                .and_then(| range | self.tags.get( range ) )
+                .unwrap_or( &[] )
+        }
 
-            // Find IDP3 in file and read the following header
-            let ident_val = [ 73 as u8, 68, 80, 51 ];
-            let mut ident_idx : usize = 0;
-            let mut tmp_offset = 0;
+        /// Looks up the tag called `name` (e.g. "tag_weapon") at `frame`.
+        pub fn tag( &self, frame : usize, name : &str ) -> Option<&Md3Tag>
+        {
+            self.frame_tags( frame ).iter().find(| t | t.name_str() == name )
+        }
 
-            let mut b : [u8; 1] = [0];
-            loop {
-                fin.read( &mut b ).ok().unwrap();
-                if ident_val[ident_idx] == b[0]{
-                    ident_idx += 1;
-                }
-                tmp_offset += 1;
-                if ident_idx == 3 {
-                    tmp_offset += 1;
-                    break;
-                }
+        /// The tag called `name` blended between two frames (R_LerpTag).
+        pub fn lerp_tag( &self, frame_a : usize, frame_b : usize, t : f32, name : &str ) -> Option<Md3Tag>
+        {
+            match ( self.tag( frame_a, name ), self.tag( frame_b, name ) ) {
+                ( Some( a ), Some( b ) ) => Some( a.interpolate( b, t ) ),
+                _ => None
             }
+        }
+
+        /// Loads a model from a file on disk.
+        pub fn load( fname : String ) -> Result<Md3Model, Md3Error>
+        {
+            use std::io::BufReader;
 
-            fin.seek( SeekFrom::Start( tmp_offset ) ).ok().unwrap();
+            let _fh = File::open(fname).map_err( lump_err( "file", 0 ) )?;
+            Md3Model::from_reader( &mut BufReader::new( _fh ) )
+        }
+
+        /// Loads a model from an in-memory MD3 image.
+        pub fn from_bytes( bytes : &[u8] ) -> Result<Md3Model, Md3Error>
+        {
+            Md3Model::from_reader( &mut io::Cursor::new( bytes ) )
+        }
+
+        /// Loads a model from any seekable stream. The stream must start with the
+        /// IDP3 header; offsets inside the MD3 are taken relative to it.
+        pub fn from_reader<R : Read + Seek>( fin : &mut R ) -> Result<Md3Model, Md3Error>
+        {
+            Md3Model::from_reader_with_mode( fin, LoadMode::Strict )
+        }
+
+        /// Like `from_reader`, but lets the caller opt into `LoadMode::Embedded`.
+        pub fn from_reader_with_mode<R : Read + Seek>( fin : &mut R, mode : LoadMode ) -> Result<Md3Model, Md3Error>
+        {
+            let stream_len = fin.seek( SeekFrom::End( 0 ) ).map_err( lump_err( "file", 0 ) )?;
+            seek_to( fin, "file", 0 )?;
+
+            let base = match mode {
+                LoadMode::Strict => 0,
+                LoadMode::Embedded => find_magic( fin )?
+            };
 
-            println!("MD3 IDENT OFFSET: {}", tmp_offset);
+            seek_to( fin, "Md3Header", base )?;
             let mut m = Md3Model {
-                header: Md3Header::read_from( &mut fin ),
+                header: Md3Header::read_from( fin, base )?,
                 frames:      vec![],
                 surfaces:    vec![],
+                tags:        vec![],
                 st_buffer:   vec![],
                 xyz_normals: vec![],
                 shaders:     vec![]
@@ -615,23 +1045,274 @@ pub mod md3 {
             // fin.seek( SeekFrom::Start( m.header.frames_offset as u64) )
             //     .expect("Could not seek to frames offset!"); 
 
+            let frames_start = resolve_offset( "Md3Frame", base, m.header.frames_offset, stream_len )?;
+            let tags_start = resolve_offset( "Md3Tag", base, m.header.tags_offset, stream_len )?;
+            let surfaces_start = resolve_offset( "Md3Surface", base, m.header.surfaces_offset, stream_len )?;
+            check_lump( "Md3Frame", frames_start, m.header.frame_count, MD3_FRAME_SIZE, stream_len )?;
+            let total_tags = if m.header.frame_count < 0 { None } else {
+                m.header.frame_count.checked_mul( m.header.tags_count )
+            };
+            let total_tags = match total_tags {
+                Some( n ) => n,
+                None => return Err( Md3Error::CountOverflow {
+                    structure: "Md3Tag", offset: tags_start,
+                    count: m.header.frame_count as i64 * m.header.tags_count as i64
+                } )
+            };
+            check_lump( "Md3Tag", tags_start, total_tags, MD3_TAG_SIZE, stream_len )?;
+            if m.header.surface_count < 0 {
+                return Err( Md3Error::CountOverflow { structure: "Md3Surface", offset: surfaces_start,
+                                                      count: m.header.surface_count as i64 } );
+            }
+
             Md3Frame::read_from (
-                &mut fin, m.header.frames_offset as u64,
+                fin, frames_start,
                 &mut m.frames, m.header.frame_count
-            );
+            )?;
+
+            Md3Tag::read_from (
+                fin, tags_start,
+                &mut m.tags, total_tags
+            )?;
 
             Md3Surface::read_from (
-                &mut fin, m.header.surfaces_offset as u64, 
-                &mut m.surfaces, m.header.surface_count
-            );
+                fin, surfaces_start,
+                &mut m.surfaces, m.header.surface_count, stream_len
+            )?;
 
-            return Some(m);
+            Ok( m )
         }
     }
 
 
-}
+    // Writing
 
+    /// Writes `name` into a fixed-size, NUL-padded field of `len` bytes,
+    /// truncating so that the field always ends in a NUL.
+    fn write_qpath<W : Write>( out : &mut W, name : &[u8], len : usize ) -> io::Result<()>
+    {
+        let name = qpath_bytes( name );
+        let n = name.len().min( len - 1 );
+        out.write_all( &name[..n] )?;
+        out.write_all( &vec![0u8; len - n] )
+    }
 
+    fn qpath_bytes( name : &[u8] ) -> &[u8]
+    {
+        let len = name.iter().position(|&c| c == 0).unwrap_or( name.len() );
+        &name[..len]
+    }
+
+    impl Vec3
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            out.write_f32::<LittleEndian>( self.x )?;
+            out.write_f32::<LittleEndian>( self.y )?;
+            out.write_f32::<LittleEndian>( self.z )
+        }
+    }
+
+    impl Md3Frame
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            self.bounds[0].write_to( out )?;
+            self.bounds[1].write_to( out )?;
+            self.local_origin.write_to( out )?;
+            out.write_f32::<LittleEndian>( self.radius )?;
+            write_qpath( out, &self.name, 16 )
+        }
+    }
+
+    impl Md3Tag
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            write_qpath( out, &self.name, MAX_QPATH )?;
+            self.origin.write_to( out )?;
+            self.axis[0].write_to( out )?;
+            self.axis[1].write_to( out )?;
+            self.axis[2].write_to( out )
+        }
+    }
+
+    impl Md3Shader
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            write_qpath( out, &self.name, MAX_QPATH )?;
+            out.write_i32::<LittleEndian>( self.shader_index )
+        }
+    }
+
+    impl Md3Triangle
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            for idx in self.indices.iter() {
+                out.write_i32::<LittleEndian>( *idx )?;
+            }
+            Ok( () )
+        }
+    }
 
+    impl Md3St
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            out.write_f32::<LittleEndian>( self.st[0] )?;
+            out.write_f32::<LittleEndian>( self.st[1] )
+        }
+    }
 
+    impl Md3XyzNormal
+    {
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            for c in self.xyz.iter() {
+                out.write_i16::<LittleEndian>( *c )?;
+            }
+            out.write_i16::<LittleEndian>( self.normal )
+        }
+    }
+
+    impl Md3Surface
+    {
+        /// Makes sure the per-vertex lumps agree with the header before writing.
+        fn check_counts( &self, offset : u64 ) -> Result<(), Md3Error>
+        {
+            let verts = self.header.vertex_count as i64;
+            if self.data.st_data.len() as i64 != verts {
+                return Err( Md3Error::CountMismatch { structure: "Md3St", offset,
+                                                      expected: verts, found: self.data.st_data.len() as i64 } );
+            }
+            let xyzn = verts * self.header.frame_count as i64;
+            if self.data.xyz_normals.len() as i64 != xyzn {
+                return Err( Md3Error::CountMismatch { structure: "Md3XyzNormal", offset,
+                                                      expected: xyzn, found: self.data.xyz_normals.len() as i64 } );
+            }
+            Ok( () )
+        }
+
+        /// Size in bytes of this surface once written, header included.
+        fn written_size( &self ) -> u64
+        {
+            MD3_SURFACE_HEADER_SIZE
+                + self.data.shaders.len() as u64 * MD3_SHADER_SIZE
+                + self.data.triangles.len() as u64 * MD3_TRIANGLE_SIZE
+                + self.data.st_data.len() as u64 * MD3_ST_SIZE
+                + self.data.xyz_normals.len() as u64 * MD3_XYZN_SIZE
+        }
+
+        /// Writes the surface in id's layout: header, shaders, triangles, ST, XYZ normals.
+        /// All lump offsets are recomputed relative to the surface start.
+        fn write_to<W : Write>( &self, out : &mut W ) -> io::Result<()>
+        {
+            let shaders_offset   = MD3_SURFACE_HEADER_SIZE;
+            let triangles_offset = shaders_offset + self.data.shaders.len() as u64 * MD3_SHADER_SIZE;
+            let st_offset        = triangles_offset + self.data.triangles.len() as u64 * MD3_TRIANGLE_SIZE;
+            let xyzn_offset      = st_offset + self.data.st_data.len() as u64 * MD3_ST_SIZE;
+            let end_offset       = self.written_size();
+
+            out.write_all( &MD3_IDENT )?;
+            write_qpath( out, &self.header.name, MAX_QPATH )?;
+            for v in [
+                self.header.flags,
+                self.header.frame_count,
+                self.data.shaders.len() as i32,
+                self.header.vertex_count,
+                self.data.triangles.len() as i32,
+                triangles_offset as i32,
+                shaders_offset as i32,
+                st_offset as i32,
+                xyzn_offset as i32,
+                end_offset as i32
+            ].iter() {
+                out.write_i32::<LittleEndian>( *v )?;
+            }
+
+            for shdr in self.data.shaders.iter() { shdr.write_to( out )?; }
+            for tri in self.data.triangles.iter() { tri.write_to( out )?; }
+            for st in self.data.st_data.iter() { st.write_to( out )?; }
+            for xyzn in self.data.xyz_normals.iter() { xyzn.write_to( out )?; }
+            Ok( () )
+        }
+    }
+
+    impl Md3Model
+    {
+        /// Serializes the model as an IDP3 version 15 file.
+        ///
+        /// Frame, tag and surface counts are taken from the loaded data and every
+        /// offset in the file and surface headers is recomputed, so the model may
+        /// be freely edited before saving. Offsets are relative to the stream
+        /// position at the time of the call.
+        pub fn write_to<W : Write + Seek>( &self, out : &mut W ) -> Result<(), Md3Error>
+        {
+            let start = out.stream_position().map_err( lump_err( "Md3Header", 0 ) )?;
+            let tags_count = if self.frames.is_empty() { 0 } else { self.tags.len() / self.frames.len() };
+
+            let frames_offset   = MD3_HEADER_SIZE;
+            let tags_offset     = frames_offset + self.frames.len() as u64 * MD3_FRAME_SIZE;
+            let surfaces_offset = tags_offset + self.tags.len() as u64 * MD3_TAG_SIZE;
+            let eof_offset      = surfaces_offset
+                + self.surfaces.iter().map(| s | s.written_size() ).sum::<u64>();
+
+            // Refuse inconsistent counts up front rather than leave a half-written
+            // file, or one the loader would reject
+            if self.tags.len() != tags_count * self.frames.len() {
+                return Err( Md3Error::CountMismatch { structure: "Md3Tag", offset: start + tags_offset,
+                                                      expected: ( tags_count * self.frames.len() ) as i64,
+                                                      found: self.tags.len() as i64 } );
+            }
+            let mut surf_offset = start + surfaces_offset;
+            for surf in self.surfaces.iter() {
+                if surf.header.frame_count as i64 != self.frames.len() as i64 {
+                    return Err( Md3Error::CountMismatch { structure: "Md3Surface", offset: surf_offset,
+                                                          expected: self.frames.len() as i64,
+                                                          found: surf.header.frame_count as i64 } );
+                }
+                surf.check_counts( surf_offset )?;
+                surf_offset += surf.written_size();
+            }
+
+            {
+                let err = lump_err( "Md3Header", start );
+                out.write_all( &MD3_IDENT ).map_err( &err )?;
+                out.write_i32::<LittleEndian>( MD3_VERSION ).map_err( &err )?;
+                write_qpath( out, &self.header.name, MAX_QPATH ).map_err( &err )?;
+                for v in [
+                    self.header.flags,
+                    self.frames.len() as i32,
+                    tags_count as i32,
+                    self.surfaces.len() as i32,
+                    self.header.skin_count,
+                    frames_offset as i32,
+                    tags_offset as i32,
+                    surfaces_offset as i32,
+                    eof_offset as i32
+                ].iter() {
+                    out.write_i32::<LittleEndian>( *v ).map_err( &err )?;
+                }
+            }
+
+            for ( i, frm ) in self.frames.iter().enumerate() {
+                frm.write_to( out )
+                    .map_err( lump_err( "Md3Frame", start + frames_offset + i as u64 * MD3_FRAME_SIZE ) )?;
+            }
+            for ( i, tag ) in self.tags.iter().enumerate() {
+                tag.write_to( out )
+                    .map_err( lump_err( "Md3Tag", start + tags_offset + i as u64 * MD3_TAG_SIZE ) )?;
+            }
+            let mut surf_offset = start + surfaces_offset;
+            for surf in self.surfaces.iter() {
+                surf.write_to( out ).map_err( lump_err( "Md3Surface", surf_offset ) )?;
+                surf_offset += surf.written_size();
+            }
+            Ok( () )
+        }
+    }
+
+
+}
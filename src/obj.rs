@@ -0,0 +1,301 @@
+// Wavefront OBJ export of a single frame, for inspection in DCC tools and
+// for text diffs in tests. Each surface is written as its own group using a
+// material named after the surface's shader; `export_mtl` writes the
+// matching .mtl library.
+//
+// `import_obj` goes the other way for static props: one surface per
+// `usemtl` material, with the material name used as the shader.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::Path;
+use import::{build_model, ImportError, MeshSurface};
+use math::Vec3;
+use md3::Md3Model;
+use skin::Skin;
+
+/// Material name for a shader path; OBJ names cannot contain whitespace.
+fn material_name( shader : &str ) -> String
+{
+    shader.chars().map(| c | if c.is_whitespace() { '_' } else { c } ).collect()
+}
+
+impl Md3Model
+{
+    /// Writes `frame` as OBJ, with materials named after the md3's own shaders.
+    pub fn export_obj<W : Write>( &self, frame : usize, out : &mut W ) -> io::Result<()>
+    {
+        self.export_obj_with( frame, out, None, None )
+    }
+
+    /// Like `export_obj`, referencing `mtllib` from the file when given and
+    /// binding materials through `skin`. UVs are flipped to OBJ's bottom-left origin.
+    /// Triangles are rewound from MD3's clockwise fronts to OBJ's
+    /// counter-clockwise ones. Fails with `InvalidInput`, before writing
+    /// anything, if `frame` is out of range, a surface lacks a position or UV
+    /// for some vertex or a triangle index lies outside its surface.
+    pub fn export_obj_with<W : Write>( &self, frame : usize, out : &mut W, mtllib : Option<&str>,
+                                       skin : Option<&Skin> ) -> io::Result<()>
+    {
+        if frame >= self.frames.len() {
+            return Err( io::Error::new( io::ErrorKind::InvalidInput,
+                                        format!( "frame {} out of range, model has {}", frame, self.frames.len() ) ) );
+        }
+        for surf in self.surfaces.iter() {
+            let vertex_count = surf.header.vertex_count.max( 0 ) as usize;
+            if surf.frame_vertices( frame ).len() != vertex_count {
+                return Err( io::Error::new( io::ErrorKind::InvalidInput,
+                                            format!( "surface {} has no positions for frame {}", surf.header.name_str(), frame ) ) );
+            }
+            if surf.data.st_data.len() != vertex_count {
+                return Err( io::Error::new( io::ErrorKind::InvalidInput,
+                                            format!( "surface {} has {} vertices but {} texture coordinates",
+                                                     surf.header.name_str(), vertex_count, surf.data.st_data.len() ) ) );
+            }
+            if let Some( &i ) = surf.data.triangles.iter().flat_map(| t | t.indices.iter() )
+                .find(| &&i | i < 0 || i as usize >= vertex_count ) {
+                return Err( io::Error::new( io::ErrorKind::InvalidInput,
+                                            format!( "surface {} has index {} outside its {} vertices",
+                                                     surf.header.name_str(), i, vertex_count ) ) );
+            }
+        }
+
+        writeln!( out, "# {} frame {}", self.name_str(), frame )?;
+        if let Some( lib ) = mtllib {
+            writeln!( out, "mtllib {}", lib )?;
+        }
+
+        // OBJ indices are 1-based and global to the file
+        let mut base = 1;
+        for surf in self.surfaces.iter() {
+            let verts = surf.frame_vertices( frame );
+            writeln!( out, "g {}", surf.header.name_str() )?;
+            if let Some( shader ) = surf.resolve_shader( skin ) {
+                writeln!( out, "usemtl {}", material_name( shader ) )?;
+            }
+            for v in verts.iter() {
+                let p = v.decode_xyz();
+                writeln!( out, "v {} {} {}", p[0], p[1], p[2] )?;
+            }
+            for v in verts.iter() {
+                let n = v.decode_normal();
+                writeln!( out, "vn {} {} {}", n[0], n[1], n[2] )?;
+            }
+            for st in surf.data.st_data.iter() {
+                writeln!( out, "vt {} {}", st.st[0], 1.0 - st.st[1] )?;
+            }
+            for tri in surf.data.triangles.iter() {
+                let i = [ base + tri.indices[0], base + tri.indices[1], base + tri.indices[2] ];
+                writeln!( out, "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}", i[0], i[2], i[1] )?;
+            }
+            base += verts.len() as i32;
+        }
+        Ok( () )
+    }
+
+    /// Writes one material per distinct surface shader, using the shader
+    /// path as the diffuse map.
+    pub fn export_mtl<W : Write>( &self, out : &mut W, skin : Option<&Skin> ) -> io::Result<()>
+    {
+        let mut written : Vec<&str> = vec![];
+        for surf in self.surfaces.iter() {
+            let shader = match surf.resolve_shader( skin ) {
+                Some( s ) => s,
+                None => continue
+            };
+            if written.contains( &shader ) {
+                continue;
+            }
+            written.push( shader );
+            writeln!( out, "newmtl {}", material_name( shader ) )?;
+            writeln!( out, "Kd 1 1 1" )?;
+            writeln!( out, "map_Kd {}", shader )?;
+            writeln!( out )?;
+        }
+        Ok( () )
+    }
+}
+
+/// Vertices of one material, deduplicated on their (v, vt, vn) triple.
+struct ObjSurface
+{
+    mesh : MeshSurface,
+    lookup : HashMap<( usize, Option<usize>, Option<usize> ), u32>,
+    /// Vertices that had no `vn` and get an averaged face normal
+    needs_normal : Vec<bool>,
+}
+
+/// Resolves a 1-based, possibly negative (relative) OBJ index.
+fn obj_index( tok : &str, len : usize, line : usize ) -> Result<usize, ImportError>
+{
+    let bad = || ImportError::Parse { line, message: format!( "bad index \"{}\"", tok ) };
+    let i : i64 = tok.parse().map_err(| _ | bad() )?;
+    let idx = if i < 0 { len as i64 + i } else { i - 1 };
+    if idx < 0 || idx as usize >= len {
+        return Err( bad() );
+    }
+    Ok( idx as usize )
+}
+
+fn parse_floats<'a, I : Iterator<Item = &'a str>>( it : I, n : usize, line : usize ) -> Result<Vec<f32>, ImportError>
+{
+    let vals : Vec<f32> = it.take( n ).map(| t | t.parse() ).collect::<Result<_, _>>()
+        .map_err(| _ | ImportError::Parse { line, message: "bad number".to_string() } )?;
+    if vals.len() < n {
+        return Err( ImportError::Parse { line, message: "missing component".to_string() } );
+    }
+    Ok( vals )
+}
+
+impl Md3Model
+{
+    /// Builds a single-frame model called `name` from OBJ text. Polygons are
+    /// fan-triangulated and rewound to MD3's clockwise fronts, V texture
+    /// coordinates are flipped to MD3's top-left origin and vertices without
+    /// normals get the average of their faces' normals.
+    pub fn import_obj<R : BufRead>( inp : R, name : &str ) -> Result<Md3Model, ImportError>
+    {
+        let mut positions : Vec<[f32; 3]> = vec![];
+        let mut normals : Vec<[f32; 3]> = vec![];
+        let mut texcoords : Vec<[f32; 2]> = vec![];
+        let mut surfaces : Vec<ObjSurface> = vec![];
+        let mut current : Option<usize> = None;
+
+        for ( n, line ) in inp.lines().enumerate() {
+            let line = line?;
+            let line_no = n + 1;
+            let mut toks = line.split_whitespace();
+            match toks.next() {
+                Some( "v" ) => {
+                    let v = parse_floats( toks, 3, line_no )?;
+                    positions.push( [ v[0], v[1], v[2] ] );
+                },
+                Some( "vn" ) => {
+                    let v = parse_floats( toks, 3, line_no )?;
+                    normals.push( [ v[0], v[1], v[2] ] );
+                },
+                Some( "vt" ) => {
+                    let v = parse_floats( toks, 2, line_no )?;
+                    texcoords.push( [ v[0], 1.0 - v[1] ] );
+                },
+                Some( "usemtl" ) => {
+                    let mtl = toks.next().unwrap_or( "" );
+                    current = match surfaces.iter().position(| s | s.mesh.material == mtl ) {
+                        Some( i ) => Some( i ),
+                        None => {
+                            surfaces.push( ObjSurface::new( mtl, surfaces.len() ) );
+                            Some( surfaces.len() - 1 )
+                        }
+                    };
+                },
+                Some( "f" ) => {
+                    let cur = match current {
+                        Some( c ) => c,
+                        None => {
+                            surfaces.push( ObjSurface::new( "", surfaces.len() ) );
+                            current = Some( surfaces.len() - 1 );
+                            surfaces.len() - 1
+                        }
+                    };
+                    let surf = &mut surfaces[cur];
+                    let mut corners = vec![];
+                    for tok in toks {
+                        let mut parts = tok.split( '/' );
+                        let v = obj_index( parts.next().unwrap_or( "" ), positions.len(), line_no )?;
+                        let vt = match parts.next() {
+                            Some( t ) if !t.is_empty() => Some( obj_index( t, texcoords.len(), line_no )? ),
+                            _ => None
+                        };
+                        let vn = match parts.next() {
+                            Some( t ) if !t.is_empty() => Some( obj_index( t, normals.len(), line_no )? ),
+                            _ => None
+                        };
+                        corners.push( surf.vertex( ( v, vt, vn ), &positions, &texcoords, &normals ) );
+                    }
+                    if corners.len() < 3 {
+                        return Err( ImportError::Parse { line: line_no, message: "face with fewer than 3 vertices".to_string() } );
+                    }
+                    for i in 1 .. corners.len() - 1 {
+                        surf.mesh.triangles.push( [ corners[0], corners[i], corners[i + 1] ] );
+                    }
+                },
+                // Comments, groups, smoothing groups, mtllib...
+                _ => {}
+            }
+        }
+
+        let meshes = surfaces.into_iter()
+            .filter(| s | !s.mesh.triangles.is_empty() )
+            .map(| s | s.finish() )
+            .collect();
+        build_model( name, meshes )
+    }
+
+    /// Imports an OBJ file, naming the model after the file.
+    pub fn load_obj( fname : String ) -> Result<Md3Model, ImportError>
+    {
+        let name = Path::new( &fname ).file_stem().map(| s | s.to_string_lossy().into_owned() ).unwrap_or_default();
+        Md3Model::import_obj( io::BufReader::new( File::open( &fname )? ), &name )
+    }
+}
+
+impl ObjSurface
+{
+    fn new( material : &str, index : usize ) -> ObjSurface
+    {
+        ObjSurface {
+            mesh: MeshSurface {
+                name: format!( "surface{}", index ),
+                material: material.to_string(),
+                positions: vec![ vec![] ],
+                normals: vec![ vec![] ],
+                uvs: vec![],
+                triangles: vec![],
+            },
+            lookup: HashMap::new(),
+            needs_normal: vec![],
+        }
+    }
+
+    fn vertex( &mut self, key : ( usize, Option<usize>, Option<usize> ),
+               positions : &[[f32; 3]], texcoords : &[[f32; 2]], normals : &[[f32; 3]] ) -> u32
+    {
+        if let Some( &i ) = self.lookup.get( &key ) {
+            return i;
+        }
+        let i = self.mesh.uvs.len() as u32;
+        self.mesh.positions[0].push( positions[key.0] );
+        self.mesh.uvs.push( key.1.map_or( [0.0, 0.0], | t | texcoords[t] ) );
+        // Files do not always write unit normals; encode_normal needs them
+        self.mesh.normals[0].push( key.2.map_or( [0.0, 0.0, 0.0], | n | Vec3::from_array( normals[n] ).normalize().to_array() ) );
+        self.needs_normal.push( key.2.is_none() );
+        self.lookup.insert( key, i );
+        i
+    }
+
+    fn finish( mut self ) -> MeshSurface
+    {
+        if self.needs_normal.iter().any(| &b | b ) {
+            let mut acc = vec![ Vec3::new( 0.0, 0.0, 0.0 ); self.needs_normal.len() ];
+            for tri in self.mesh.triangles.iter() {
+                let p : Vec<Vec3> = tri.iter().map(| &i | Vec3::from_array( self.mesh.positions[0][i as usize] ) ).collect();
+                let face = ( p[1] - p[0] ).cross( p[2] - p[0] );
+                for &i in tri.iter() {
+                    acc[i as usize] = acc[i as usize] + face;
+                }
+            }
+            for ( i, n ) in acc.into_iter().enumerate() {
+                if self.needs_normal[i] {
+                    self.mesh.normals[0][i] = n.normalize().to_array();
+                }
+            }
+        }
+        // OBJ fronts wind counter-clockwise, MD3's clockwise
+        for tri in self.mesh.triangles.iter_mut() {
+            tri.swap( 1, 2 );
+        }
+        self.mesh
+    }
+}
@@ -0,0 +1,170 @@
+// Quake 3 .pk3 archives, which are plain zip files.
+//
+// Entry names are looked up the way the engine's filesystem does it:
+// case-insensitively and with '\' treated as '/', so `Models/Players/..`
+// written by a Windows zip tool still resolves.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use zip::ZipArchive;
+use zip::result::ZipError;
+use md3::{Md3Error, Md3Model};
+
+/// Most bytes reserved up front for an entry, so a forged size in the
+/// archive's directory cannot force a huge allocation. Larger entries still
+/// read in full, the buffer growing as needed.
+const READ_CAPACITY_HINT : u64 = 1 << 20;
+
+#[derive(Debug)]
+pub enum Pk3Error
+{
+    Io( io::Error ),
+    /// The archive or one of its entries is not valid zip data
+    Zip( ZipError ),
+    /// No entry with that name
+    NotFound( String ),
+    /// The entry was found but is not a loadable md3
+    Md3 { name : String, error : Md3Error },
+}
+
+impl fmt::Display for Pk3Error
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            Pk3Error::Io( ref e ) => write!( f, "I/O error: {}", e ),
+            Pk3Error::Zip( ref e ) => write!( f, "bad pk3: {}", e ),
+            Pk3Error::NotFound( ref name ) => write!( f, "{} not found in pk3", name ),
+            Pk3Error::Md3 { ref name, ref error } => write!( f, "{}: {}", name, error ),
+        }
+    }
+}
+
+impl Error for Pk3Error
+{
+    fn source( &self ) -> Option<&(dyn Error + 'static)>
+    {
+        match *self {
+            Pk3Error::Io( ref e ) => Some( e ),
+            Pk3Error::Zip( ref e ) => Some( e ),
+            Pk3Error::Md3 { ref error, .. } => Some( error ),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for Pk3Error
+{
+    fn from( e : io::Error ) -> Pk3Error
+    {
+        Pk3Error::Io( e )
+    }
+}
+
+impl From<ZipError> for Pk3Error
+{
+    fn from( e : ZipError ) -> Pk3Error
+    {
+        match e {
+            ZipError::Io( e ) => Pk3Error::Io( e ),
+            e => Pk3Error::Zip( e )
+        }
+    }
+}
+
+/// Lowercases and turns backslashes into slashes, as FS_HashFileName does.
+pub fn normalize_path( name : &str ) -> String
+{
+    name.chars().map(| c | if c == '\\' { '/' } else { c.to_ascii_lowercase() } ).collect()
+}
+
+pub struct Pk3<R = File>
+{
+    archive : ZipArchive<R>,
+    /// File entries in archive order, as stored
+    names : Vec<String>,
+    /// Normalized name to archive index
+    index : HashMap<String, usize>,
+}
+
+impl Pk3<File>
+{
+    pub fn open( fname : String ) -> Result<Pk3<File>, Pk3Error>
+    {
+        Pk3::from_reader( File::open( fname )? )
+    }
+}
+
+impl<R : Read + Seek> Pk3<R>
+{
+    pub fn from_reader( inp : R ) -> Result<Pk3<R>, Pk3Error>
+    {
+        let archive = ZipArchive::new( inp )?;
+        let mut names = vec![];
+        let mut index = HashMap::new();
+        for i in 0 .. archive.len() {
+            let name = match archive.name_for_index( i ) {
+                Some( name ) if !name.ends_with( '/' ) => name,
+                _ => continue
+            };
+            // on duplicates the first entry wins, as in the engine's hash chain walk
+            index.entry( normalize_path( name ) ).or_insert( i );
+            names.push( name.to_string() );
+        }
+        Ok( Pk3 { archive, names, index } )
+    }
+
+    /// Names of all file entries, directories excluded, in archive order.
+    pub fn entries( &self ) -> &[String]
+    {
+        &self.names
+    }
+
+    /// Entries under `models/` with an .md3 extension.
+    pub fn models( &self ) -> Vec<&str>
+    {
+        self.names.iter()
+            .filter(| name | {
+                let name = normalize_path( name );
+                name.starts_with( "models/" ) && name.ends_with( ".md3" )
+            })
+            .map(| name | name.as_str() )
+            .collect()
+    }
+
+    pub fn contains( &self, name : &str ) -> bool
+    {
+        self.index.contains_key( &normalize_path( name ) )
+    }
+
+    /// Decompressed contents of the entry `name`.
+    pub fn read( &mut self, name : &str ) -> Result<Vec<u8>, Pk3Error>
+    {
+        let i = match self.index.get( &normalize_path( name ) ) {
+            Some( &i ) => i,
+            None => return Err( Pk3Error::NotFound( name.to_string() ) )
+        };
+        let mut entry = self.archive.by_index( i )?;
+        let mut bytes = Vec::with_capacity( entry.size().min( READ_CAPACITY_HINT ) as usize );
+        entry.read_to_end( &mut bytes )?;
+        Ok( bytes )
+    }
+
+    /// Entry `name` as UTF-8 text, for skins, shaders and animation.cfg.
+    pub fn read_to_string( &mut self, name : &str ) -> Result<String, Pk3Error>
+    {
+        let bytes = self.read( name )?;
+        Ok( String::from_utf8_lossy( &bytes ).into_owned() )
+    }
+
+    pub fn load_md3( &mut self, name : &str ) -> Result<Md3Model, Pk3Error>
+    {
+        let bytes = self.read( name )?;
+        Md3Model::from_bytes( &bytes )
+            .map_err(| error | Pk3Error::Md3 { name: name.to_string(), error } )
+    }
+}
@@ -0,0 +1,66 @@
+// Quake 3 player models: lower.md3 (legs), upper.md3 (torso) and head.md3,
+// chained through tag_torso on the legs and tag_head on the torso. Legs and
+// torso animate independently; the head has a single frame and follows the
+// torso.
+
+use std::path::Path;
+use math::Transform;
+use md3::{Md3Error, Md3Model};
+use vfs::{Vfs, VfsError};
+
+// Lives with the clips it samples; re-exported for existing users
+pub use animation::FrameLerp;
+
+/// Placement of each part relative to the player's origin.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PlayerTransforms
+{
+    pub lower : Transform,
+    pub upper : Transform,
+    pub head : Transform,
+}
+
+pub struct PlayerModel
+{
+    pub lower : Md3Model,
+    pub upper : Md3Model,
+    pub head : Md3Model,
+}
+
+impl PlayerModel
+{
+    /// Loads lower.md3, upper.md3 and head.md3 from a player directory
+    /// such as `models/players/sarge`.
+    pub fn load( dir : String ) -> Result<PlayerModel, Md3Error>
+    {
+        let part = | name : &str | Path::new( &dir ).join( name ).to_string_lossy().into_owned();
+        Ok( PlayerModel {
+            lower: Md3Model::load( part( "lower.md3" ) )?,
+            upper: Md3Model::load( part( "upper.md3" ) )?,
+            head: Md3Model::load( part( "head.md3" ) )?,
+        } )
+    }
+
+    /// Like `load`, with `dir` resolved through the game filesystem.
+    pub fn load_from( vfs : &mut Vfs, dir : &str ) -> Result<PlayerModel, VfsError>
+    {
+        let dir = dir.trim_end_matches( '/' );
+        Ok( PlayerModel {
+            lower: vfs.load_md3( &format!( "{}/lower.md3", dir ) )?,
+            upper: vfs.load_md3( &format!( "{}/upper.md3", dir ) )?,
+            head: vfs.load_md3( &format!( "{}/head.md3", dir ) )?,
+        } )
+    }
+
+    /// Composes the tag chain for the given legs and torso frames, with the
+    /// legs placed at `root`. Returns `None` if tag_torso or tag_head is missing.
+    pub fn transforms( &self, root : &Transform, legs : FrameLerp, torso : FrameLerp ) -> Option<PlayerTransforms>
+    {
+        let tag_torso = self.lower.lerp_tag( legs.frame_a, legs.frame_b, legs.t, "tag_torso" )?;
+        let tag_head = self.upper.lerp_tag( torso.frame_a, torso.frame_b, torso.t, "tag_head" )?;
+
+        let upper = root.compose( &tag_torso.transform() );
+        let head = upper.compose( &tag_head.transform() );
+        Some( PlayerTransforms { lower: *root, upper, head } )
+    }
+}
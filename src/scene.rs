@@ -0,0 +1,127 @@
+// Tag attachment graph: weapons on tag_weapon, flashes on tag_flash, barrels
+// on tag_barrel, or any other md3 hung off any other md3's tag.
+
+use std::error::Error;
+use std::fmt;
+use std::rc::Rc;
+use animation::FrameLerp;
+use math::Transform;
+use md3::Md3Model;
+
+pub type NodeId = usize;
+
+pub struct SceneNode
+{
+    pub model : Rc<Md3Model>,
+    /// Parent node and the name of the parent's tag this node hangs from
+    pub parent : Option<( NodeId, String )>,
+    /// Offset applied after the tag (e.g. a spinning barrel), or the world
+    /// placement for root nodes
+    pub local : Transform,
+    /// Frames the model is currently showing; drives the tags of its children
+    pub frame : FrameLerp,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum SceneError
+{
+    /// The parent model has no tag with this name at the frames being shown
+    MissingTag { node : NodeId, tag : String },
+    /// A node's parent does not come before it in `nodes`, as `attach`
+    /// guarantees but direct edits to `nodes` may not
+    BadParent { node : NodeId, parent : NodeId },
+}
+
+impl fmt::Display for SceneError
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            SceneError::MissingTag { node, ref tag } =>
+                write!( f, "parent of scene node {} has no tag \"{}\"", node, tag ),
+            SceneError::BadParent { node, parent } =>
+                write!( f, "scene node {} has parent {}, which does not come before it", node, parent ),
+        }
+    }
+}
+
+impl Error for SceneError {}
+
+/// Nodes are stored in insertion order and a node can only be attached to
+/// an existing one, so parents always come before their children.
+#[derive(Default)]
+pub struct SceneGraph
+{
+    pub nodes : Vec<SceneNode>,
+}
+
+impl SceneGraph
+{
+    pub fn new() -> SceneGraph
+    {
+        SceneGraph { nodes: vec![] }
+    }
+
+    /// Adds an unparented node placed at `transform`.
+    pub fn add_root( &mut self, model : Rc<Md3Model>, transform : Transform ) -> NodeId
+    {
+        self.nodes.push( SceneNode {
+            model, parent: None, local: transform, frame: FrameLerp::frame( 0 )
+        } );
+        self.nodes.len() - 1
+    }
+
+    /// Hangs `model` off the tag called `tag` of node `parent`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `parent` is not a node of this graph.
+    pub fn attach( &mut self, model : Rc<Md3Model>, parent : NodeId, tag : &str ) -> NodeId
+    {
+        assert!( parent < self.nodes.len(), "attaching to unknown scene node {}", parent );
+        self.nodes.push( SceneNode {
+            model, parent: Some( ( parent, tag.to_string() ) ),
+            local: Transform::identity(), frame: FrameLerp::frame( 0 )
+        } );
+        self.nodes.len() - 1
+    }
+
+    pub fn set_frame( &mut self, node : NodeId, frame : FrameLerp )
+    {
+        self.nodes[node].frame = frame;
+    }
+
+    pub fn set_local( &mut self, node : NodeId, local : Transform )
+    {
+        self.nodes[node].local = local;
+    }
+
+    /// World placement of every node, indexed by `NodeId`. Fails if a
+    /// parent's tag is missing or a node's parent does not precede it.
+    pub fn world_transforms( &self ) -> Result<Vec<Transform>, SceneError>
+    {
+        let mut world : Vec<Transform> = Vec::with_capacity( self.nodes.len() );
+        for ( id, node ) in self.nodes.iter().enumerate() {
+            let placed = match node.parent {
+                None => node.local,
+                Some( ( parent, ref tag ) ) => {
+                    if parent >= id {
+                        return Err( SceneError::BadParent { node: id, parent } );
+                    }
+                    let p = &self.nodes[parent];
+                    let lerped = p.model.lerp_tag( p.frame.frame_a, p.frame.frame_b, p.frame.t, tag )
+                        .ok_or_else(|| SceneError::MissingTag { node: id, tag: tag.clone() } )?;
+                    world[parent].compose( &lerped.transform() ).compose( &node.local )
+                }
+            };
+            world.push( placed );
+        }
+        Ok( world )
+    }
+
+    /// Column-major 4x4 world matrix of every node, indexed by `NodeId`.
+    pub fn world_matrices( &self ) -> Result<Vec<[[f32; 4]; 4]>, SceneError>
+    {
+        Ok( self.world_transforms()?.iter().map(| t | t.to_matrix() ).collect() )
+    }
+}
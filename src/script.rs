@@ -0,0 +1,99 @@
+// Tokenizer for the id Tech 3 text formats (animation.cfg, .skin, .shader).
+//
+// Mirrors COM_ParseExt: tokens are separated by whitespace, `//` and `/* */`
+// are comments, double quotes group a token, and callers can ask not to cross
+// a line break when a statement must stay on one line.
+
+pub struct Token<'a>
+{
+    pub text : &'a str,
+    pub line : usize,
+}
+
+pub struct Tokenizer<'a>
+{
+    src : &'a str,
+    pos : usize,
+    line : usize,
+}
+
+impl<'a> Tokenizer<'a>
+{
+    pub fn new( src : &'a str ) -> Tokenizer<'a>
+    {
+        Tokenizer { src, pos: 0, line: 1 }
+    }
+
+    /// Skips whitespace and comments. Returns false if a line break was
+    /// crossed while `allow_line_breaks` is false.
+    fn skip_blank( &mut self, allow_line_breaks : bool ) -> bool
+    {
+        let bytes = self.src.as_bytes();
+        loop {
+            while self.pos < bytes.len() && bytes[self.pos] <= b' ' {
+                if bytes[self.pos] == b'\n' {
+                    if !allow_line_breaks {
+                        return false;
+                    }
+                    self.line += 1;
+                }
+                self.pos += 1;
+            }
+            if bytes[self.pos..].starts_with( b"//" ) {
+                while self.pos < bytes.len() && bytes[self.pos] != b'\n' {
+                    self.pos += 1;
+                }
+            } else if bytes[self.pos..].starts_with( b"/*" ) {
+                self.pos += 2;
+                while self.pos < bytes.len() && !bytes[self.pos..].starts_with( b"*/" ) {
+                    if bytes[self.pos] == b'\n' {
+                        self.line += 1;
+                    }
+                    self.pos += 1;
+                }
+                self.pos = ( self.pos + 2 ).min( bytes.len() );
+            } else {
+                return true;
+            }
+        }
+    }
+
+    /// Returns the next token, or `None` at end of input or, when
+    /// `allow_line_breaks` is false, at the end of the current line.
+    pub fn next_token( &mut self, allow_line_breaks : bool ) -> Option<Token<'a>>
+    {
+        if !self.skip_blank( allow_line_breaks ) {
+            return None;
+        }
+        let src : &'a str = self.src;
+        let bytes = src.as_bytes();
+        if self.pos >= bytes.len() {
+            return None;
+        }
+        let line = self.line;
+        if bytes[self.pos] == b'"' {
+            let start = self.pos + 1;
+            let mut end = start;
+            while end < bytes.len() && bytes[end] != b'"' && bytes[end] != b'\n' {
+                end += 1;
+            }
+            self.pos = if end < bytes.len() && bytes[end] == b'"' { end + 1 } else { end };
+            return Some( Token { text: &src[start..end], line } );
+        }
+        let start = self.pos;
+        while self.pos < bytes.len() && bytes[self.pos] > b' ' {
+            self.pos += 1;
+        }
+        Some( Token { text: &src[start..self.pos], line } )
+    }
+
+    /// Returns the next token without consuming it.
+    pub fn peek_token( &mut self, allow_line_breaks : bool ) -> Option<Token<'a>>
+    {
+        let ( pos, line ) = ( self.pos, self.line );
+        let tok = self.next_token( allow_line_breaks );
+        self.pos = pos;
+        self.line = line;
+        tok
+    }
+}
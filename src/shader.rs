@@ -0,0 +1,753 @@
+// Quake 3 shader scripts (scripts/*.shader).
+//
+// A script is a list of named shaders, each a brace block of general
+// keywords followed by stage blocks:
+//
+//     models/players/sarge/band
+//     {
+//         cull disable
+//         {
+//             map models/players/sarge/band.tga
+//             blendFunc GL_ONE GL_ZERO
+//             rgbGen lightingDiffuse
+//         }
+//     }
+//
+// Keywords are tokenized the way tr_shader.c reads them: case-insensitively,
+// with arguments on the keyword's line. Editor and compiler keywords
+// (qer_*, q3map_*) and keywords not modelled here are skipped to the end of
+// the line, inside stages as well as at shader level.
+//
+// Loading warns and carries on, but its recovery is simpler and stricter
+// than the engine's: a stage with a bad value is dropped from its shader, a
+// shader with a bad general keyword is dropped from the set, and a script
+// whose braces do not close stops there. tr_shader.c differs in three ways:
+// it only warns about most bad values and keeps the shader, it fails the
+// whole shader on an unknown stage keyword, and a shader that fails to parse
+// becomes the default shader under its name rather than letting another
+// definition through. A warning here therefore marks a shader the game may
+// render differently.
+
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use pk3::normalize_path;
+use script::Tokenizer;
+use vfs::{Vfs, VfsError};
+
+#[derive(Debug)]
+pub enum ShaderError
+{
+    Io( io::Error ),
+    /// A keyword argument is missing or not one of the accepted values
+    BadValue { line : usize, token : String },
+    /// A brace or parenthesis was expected
+    Expected { line : usize, expected : &'static str, found : String },
+    /// The script ended inside the named shader's braces
+    UnexpectedEnd { shader : String },
+}
+
+impl fmt::Display for ShaderError
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            ShaderError::Io( ref e ) => write!( f, "I/O error reading shader script: {}", e ),
+            ShaderError::BadValue { line, ref token } =>
+                write!( f, "bad value \"{}\" on line {} of shader script", token, line ),
+            ShaderError::Expected { line, expected, ref found } =>
+                write!( f, "expected '{}' on line {} of shader script, found \"{}\"", expected, line, found ),
+            ShaderError::UnexpectedEnd { ref shader } =>
+                write!( f, "shader script ended inside shader {}", shader ),
+        }
+    }
+}
+
+impl Error for ShaderError
+{
+    fn source( &self ) -> Option<&(dyn Error + 'static)>
+    {
+        match *self {
+            ShaderError::Io( ref e ) => Some( e ),
+            _ => None
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum WaveFunc
+{
+    Sin,
+    Triangle,
+    Square,
+    Sawtooth,
+    InverseSawtooth,
+    Noise,
+}
+
+/// `<func> <base> <amplitude> <phase> <frequency>`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Wave
+{
+    pub func : WaveFunc,
+    pub base : f32,
+    pub amplitude : f32,
+    pub phase : f32,
+    pub frequency : f32,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Cull
+{
+    Front,
+    Back,
+    /// `cull none`, `cull disable` or `cull twosided`
+    TwoSided,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum DeformVertexes
+{
+    /// `wave <div> <wave>`; the renderer spreads the phase by 1 / div
+    Wave { div : f32, wave : Wave },
+    Normal { amplitude : f32, frequency : f32 },
+    Bulge { width : f32, height : f32, speed : f32 },
+    Move { vector : [f32; 3], wave : Wave },
+    ProjectionShadow,
+    AutoSprite,
+    AutoSprite2,
+    /// `text0` to `text7`
+    Text( u8 ),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum StageMap
+{
+    Map( String ),
+    ClampMap( String ),
+    /// Up to eight images cycled at `frequency` per second
+    AnimMap { frequency : f32, images : Vec<String> },
+    VideoMap( String ),
+    /// `map $lightmap`
+    Lightmap,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BlendFactor
+{
+    Zero,
+    One,
+    SrcColor,
+    OneMinusSrcColor,
+    DstColor,
+    OneMinusDstColor,
+    SrcAlpha,
+    OneMinusSrcAlpha,
+    DstAlpha,
+    OneMinusDstAlpha,
+    SrcAlphaSaturate,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaFunc
+{
+    Gt0,
+    Lt128,
+    Ge128,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DepthFunc
+{
+    LessEqual,
+    Equal,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum RgbGen
+{
+    Identity,
+    IdentityLighting,
+    Wave( Wave ),
+    Const( [f32; 3] ),
+    Entity,
+    OneMinusEntity,
+    Vertex,
+    ExactVertex,
+    OneMinusVertex,
+    LightingDiffuse,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum AlphaGen
+{
+    Identity,
+    Wave( Wave ),
+    Const( f32 ),
+    Entity,
+    OneMinusEntity,
+    Vertex,
+    OneMinusVertex,
+    LightingSpecular,
+    /// Fades to opaque over the given distance from a portal
+    Portal( f32 ),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TcGen
+{
+    /// `tcGen texture` or `tcGen base`
+    Texture,
+    Lightmap,
+    Environment,
+    Vector( [f32; 3], [f32; 3] ),
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TcMod
+{
+    Turb { base : f32, amplitude : f32, phase : f32, frequency : f32 },
+    Scale( [f32; 2] ),
+    Scroll( [f32; 2] ),
+    Stretch( Wave ),
+    Transform { matrix : [[f32; 2]; 2], translate : [f32; 2] },
+    /// Degrees per second
+    Rotate( f32 ),
+    EntityTranslate,
+}
+
+/// One `{ }` block inside a shader.
+#[derive(Clone, Debug, PartialEq, Default)]
+pub struct Stage
+{
+    pub map : Option<StageMap>,
+    /// Source and destination factors; `add`, `filter` and `blend` are expanded
+    pub blend_func : Option<( BlendFactor, BlendFactor )>,
+    pub rgb_gen : Option<RgbGen>,
+    pub alpha_gen : Option<AlphaGen>,
+    pub tc_gen : Option<TcGen>,
+    pub tc_mods : Vec<TcMod>,
+    pub alpha_func : Option<AlphaFunc>,
+    pub depth_func : Option<DepthFunc>,
+    pub depth_write : bool,
+    pub detail : bool,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shader
+{
+    /// As written in the script
+    pub name : String,
+    /// `surfaceparm` values, lowercased
+    pub surface_params : Vec<String>,
+    pub cull : Cull,
+    pub deforms : Vec<DeformVertexes>,
+    /// Explicit `sort`, with the named sorts mapped to the renderer's
+    /// shaderSort_t values (portal 1, sky 2, opaque 3, decal 4,
+    /// seeThrough 5, banner 6, underwater 8, additive 10, nearest 16)
+    pub sort : Option<f32>,
+    pub no_mipmaps : bool,
+    pub no_picmip : bool,
+    pub polygon_offset : bool,
+    pub stages : Vec<Stage>,
+}
+
+impl Shader
+{
+    fn new( name : &str ) -> Shader
+    {
+        Shader {
+            name: name.to_string(),
+            surface_params: vec![],
+            cull: Cull::Front,
+            deforms: vec![],
+            sort: None,
+            no_mipmaps: false,
+            no_picmip: false,
+            polygon_offset: false,
+            stages: vec![],
+        }
+    }
+
+    pub fn has_surface_param( &self, param : &str ) -> bool
+    {
+        let param = param.to_lowercase();
+        self.surface_params.contains( &param )
+    }
+
+    /// Images referenced by the stages, in stage order, `$lightmap` excluded.
+    pub fn images( &self ) -> Vec<&str>
+    {
+        let mut images = vec![];
+        for stage in self.stages.iter() {
+            match stage.map {
+                Some( StageMap::Map( ref s ) ) | Some( StageMap::ClampMap( ref s ) ) => images.push( s.as_str() ),
+                Some( StageMap::AnimMap { images: ref frames, .. } ) =>
+                    images.extend( frames.iter().map(| s | s.as_str() ) ),
+                _ => {}
+            }
+        }
+        images
+    }
+}
+
+/// Key a shader is stored and looked up under: R_FindShader lowercases and
+/// drops the extension, so `models/x/skin.tga` finds shader `models/x/skin`.
+pub fn shader_key( name : &str ) -> String
+{
+    let mut key = normalize_path( name );
+    let slash = key.rfind( '/' ).map_or( 0, | i | i + 1 );
+    if let Some( dot ) = key[slash ..].rfind( '.' ) {
+        key.truncate( slash + dot );
+    }
+    key
+}
+
+/// A problem skipped over while loading a script.
+#[derive(Debug)]
+pub struct ShaderWarning
+{
+    /// Name the script was added under
+    pub script : String,
+    pub error : ShaderError,
+}
+
+impl fmt::Display for ShaderWarning
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        write!( f, "{}: {}", self.script, self.error )
+    }
+}
+
+/// All shaders from one or more scripts, keyed by `shader_key`.
+#[derive(Default)]
+pub struct ShaderSet
+{
+    pub shaders : HashMap<String, Shader>,
+    /// Everything `add_script` skipped, in the order met
+    pub warnings : Vec<ShaderWarning>,
+}
+
+impl ShaderSet
+{
+    pub fn new() -> ShaderSet
+    {
+        ShaderSet { shaders: HashMap::new(), warnings: vec![] }
+    }
+
+    pub fn load( fname : String ) -> Result<ShaderSet, ShaderError>
+    {
+        let mut text = String::new();
+        File::open( fname ).and_then(| mut f | f.read_to_string( &mut text ) )
+            .map_err( ShaderError::Io )?;
+        ShaderSet::parse( &text )
+    }
+
+    /// Parses a single script, failing on the first problem instead of
+    /// skipping it.
+    pub fn parse( text : &str ) -> Result<ShaderSet, ShaderError>
+    {
+        let mut set = ShaderSet::new();
+        set.add_script( "", text );
+        if set.warnings.is_empty() {
+            Ok( set )
+        } else {
+            Err( set.warnings.remove( 0 ).error )
+        }
+    }
+
+    /// Every scripts/*.shader visible through `vfs`. ScanAndLoadShaderFiles
+    /// joins the scripts in reverse name order and takes the first
+    /// definition it finds, so a script sorting later overrides an earlier
+    /// one. Problems in a script are collected in `warnings`, and a
+    /// definition that fails is passed over for the next one (see the
+    /// module notes).
+    pub fn load_from( vfs : &mut Vfs ) -> Result<ShaderSet, VfsError>
+    {
+        let mut set = ShaderSet::new();
+        for name in vfs.list_files( "scripts", ".shader" ).into_iter().rev() {
+            let text = vfs.read_to_string( &name )?;
+            set.add_script( &name, &text );
+        }
+        Ok( set )
+    }
+
+    /// Parses another script into the set, recording what it skips in
+    /// `warnings` under `script`. As in the engine, the first definition of
+    /// a name wins; unlike it, only definitions that parse count, so one
+    /// that fails leaves the name to later definitions.
+    pub fn add_script( &mut self, script : &str, text : &str )
+    {
+        let mut errors = vec![];
+        let mut tok = Tokenizer::new( text );
+        while let Some( name ) = tok.next_token( true ) {
+            let open = tok.next_token( true );
+            if open.as_ref().map(| t | t.text ) != Some( "{" ) {
+                errors.push( ShaderError::Expected {
+                    line: open.as_ref().map_or( name.line, | t | t.line ),
+                    expected: "{",
+                    found: open.map(| t | t.text.to_string() ).unwrap_or_default()
+                } );
+                break;
+            }
+            match parse_shader( &mut tok, name.text, &mut errors ) {
+                Ok( shader ) => { self.shaders.entry( shader_key( name.text ) ).or_insert( shader ); },
+                Err( e ) => errors.push( e )
+            }
+        }
+        self.warnings.extend( errors.into_iter().map(| error | ShaderWarning { script: script.to_string(), error } ) );
+    }
+
+    /// Looks `name` up the way the renderer does, ignoring case and extension.
+    pub fn get( &self, name : &str ) -> Option<&Shader>
+    {
+        self.shaders.get( &shader_key( name ) )
+    }
+}
+
+fn skip_rest_of_line( tok : &mut Tokenizer )
+{
+    while tok.next_token( false ).is_some() {}
+}
+
+/// Skips the rest of the line and then past the `}` closing the block the
+/// tokenizer is inside. False if the script ends first.
+fn skip_block( tok : &mut Tokenizer ) -> bool
+{
+    skip_rest_of_line( tok );
+    let mut depth = 1;
+    while let Some( t ) = tok.next_token( true ) {
+        match t.text {
+            "{" => depth += 1,
+            "}" => {
+                depth -= 1;
+                if depth == 0 {
+                    return true;
+                }
+            },
+            _ => {}
+        }
+    }
+    false
+}
+
+fn missing( line : usize ) -> ShaderError
+{
+    ShaderError::BadValue { line, token: String::new() }
+}
+
+/// Next argument on the keyword's line, lowercased.
+fn arg( tok : &mut Tokenizer, line : usize ) -> Result<String, ShaderError>
+{
+    match tok.next_token( false ) {
+        Some( t ) => Ok( t.text.to_lowercase() ),
+        None => Err( missing( line ) )
+    }
+}
+
+fn float( tok : &mut Tokenizer, line : usize ) -> Result<f32, ShaderError>
+{
+    match tok.next_token( false ) {
+        Some( t ) => t.text.parse().map_err(| _ | ShaderError::BadValue { line: t.line, token: t.text.to_string() } ),
+        None => Err( missing( line ) )
+    }
+}
+
+fn expect( tok : &mut Tokenizer, line : usize, expected : &'static str ) -> Result<(), ShaderError>
+{
+    match tok.next_token( false ) {
+        Some( ref t ) if t.text == expected => Ok( () ),
+        t => Err( ShaderError::Expected { line, expected, found: t.map(| t | t.text.to_string() ).unwrap_or_default() } )
+    }
+}
+
+/// `( x y z )`
+fn vector( tok : &mut Tokenizer, line : usize ) -> Result<[f32; 3], ShaderError>
+{
+    expect( tok, line, "(" )?;
+    let v = [float( tok, line )?, float( tok, line )?, float( tok, line )?];
+    expect( tok, line, ")" )?;
+    Ok( v )
+}
+
+fn wave( tok : &mut Tokenizer, line : usize ) -> Result<Wave, ShaderError>
+{
+    let func = match arg( tok, line )?.as_str() {
+        "sin" => WaveFunc::Sin,
+        "triangle" => WaveFunc::Triangle,
+        "square" => WaveFunc::Square,
+        "sawtooth" => WaveFunc::Sawtooth,
+        "inversesawtooth" => WaveFunc::InverseSawtooth,
+        "noise" => WaveFunc::Noise,
+        s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+    };
+    Ok( Wave {
+        func,
+        base: float( tok, line )?,
+        amplitude: float( tok, line )?,
+        phase: float( tok, line )?,
+        frequency: float( tok, line )?,
+    } )
+}
+
+/// Parses the body of shader `name` up to its closing brace. A stage with
+/// a bad value is left out and reported in `warnings`; a bad general
+/// keyword fails the whole shader, with the rest of it skipped. Unknown
+/// keywords are skipped in both places, where the engine rejects an
+/// unknown stage keyword.
+fn parse_shader( tok : &mut Tokenizer, name : &str, warnings : &mut Vec<ShaderError> ) -> Result<Shader, ShaderError>
+{
+    let mut shader = Shader::new( name );
+    loop {
+        match parse_shader_keyword( tok, name, &mut shader, warnings ) {
+            Ok( true ) => return Ok( shader ),
+            Ok( false ) => {},
+            Err( e @ ShaderError::UnexpectedEnd { .. } ) => return Err( e ),
+            Err( e ) => {
+                if !skip_block( tok ) {
+                    warnings.push( e );
+                    return Err( ShaderError::UnexpectedEnd { shader: name.to_string() } );
+                }
+                return Err( e );
+            }
+        }
+    }
+}
+
+/// One general keyword or stage of `shader`; true at the closing brace.
+fn parse_shader_keyword( tok : &mut Tokenizer, name : &str, shader : &mut Shader,
+                         warnings : &mut Vec<ShaderError> ) -> Result<bool, ShaderError>
+{
+    let t = match tok.next_token( true ) {
+        Some( t ) => t,
+        None => return Err( ShaderError::UnexpectedEnd { shader: name.to_string() } )
+    };
+    let line = t.line;
+    let keyword = t.text.to_lowercase();
+    match keyword.as_str() {
+        "}" => return Ok( true ),
+        "{" => match parse_stage( tok, name ) {
+            Ok( stage ) => shader.stages.push( stage ),
+            Err( e @ ShaderError::UnexpectedEnd { .. } ) => return Err( e ),
+            Err( e ) => {
+                warnings.push( e );
+                if !skip_block( tok ) {
+                    return Err( ShaderError::UnexpectedEnd { shader: name.to_string() } );
+                }
+            }
+        },
+        "surfaceparm" => {
+            let param = arg( tok, line )?;
+            shader.surface_params.push( param );
+        },
+        "cull" => {
+            shader.cull = match arg( tok, line )?.as_str() {
+                "none" | "twosided" | "disable" => Cull::TwoSided,
+                "back" | "backside" | "backsided" => Cull::Back,
+                "front" => Cull::Front,
+                s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+            };
+        },
+        "deformvertexes" => {
+            let deform = parse_deform( tok, line )?;
+            shader.deforms.push( deform );
+        },
+        "sort" => {
+            shader.sort = Some( match arg( tok, line )?.as_str() {
+                "portal" => 1.0,
+                "sky" => 2.0,
+                "opaque" => 3.0,
+                "decal" => 4.0,
+                "seethrough" => 5.0,
+                "banner" => 6.0,
+                "underwater" => 8.0,
+                "additive" => 10.0,
+                "nearest" => 16.0,
+                s => s.parse().map_err(| _ | ShaderError::BadValue { line, token: s.to_string() } )?
+            } );
+        },
+        "nomipmaps" => shader.no_mipmaps = true,
+        "nopicmip" => shader.no_picmip = true,
+        "polygonoffset" => shader.polygon_offset = true,
+        _ => skip_rest_of_line( tok )
+    }
+    Ok( false )
+}
+
+fn parse_deform( tok : &mut Tokenizer, line : usize ) -> Result<DeformVertexes, ShaderError>
+{
+    let kind = arg( tok, line )?;
+    Ok( match kind.as_str() {
+        "projectionshadow" => DeformVertexes::ProjectionShadow,
+        "autosprite" => DeformVertexes::AutoSprite,
+        "autosprite2" => DeformVertexes::AutoSprite2,
+        "bulge" => DeformVertexes::Bulge {
+            width: float( tok, line )?, height: float( tok, line )?, speed: float( tok, line )?
+        },
+        "wave" => DeformVertexes::Wave { div: float( tok, line )?, wave: wave( tok, line )? },
+        "normal" => DeformVertexes::Normal { amplitude: float( tok, line )?, frequency: float( tok, line )? },
+        "move" => DeformVertexes::Move {
+            vector: [float( tok, line )?, float( tok, line )?, float( tok, line )?],
+            wave: wave( tok, line )?
+        },
+        s if s.len() == 5 && s.starts_with( "text" ) && ( b'0' ..= b'7' ).contains( &s.as_bytes()[4] ) =>
+            DeformVertexes::Text( s.as_bytes()[4] - b'0' ),
+        s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+    } )
+}
+
+fn blend_factor( name : &str, line : usize ) -> Result<BlendFactor, ShaderError>
+{
+    Ok( match name {
+        "gl_zero" => BlendFactor::Zero,
+        "gl_one" => BlendFactor::One,
+        "gl_src_color" => BlendFactor::SrcColor,
+        "gl_one_minus_src_color" => BlendFactor::OneMinusSrcColor,
+        "gl_dst_color" => BlendFactor::DstColor,
+        "gl_one_minus_dst_color" => BlendFactor::OneMinusDstColor,
+        "gl_src_alpha" => BlendFactor::SrcAlpha,
+        "gl_one_minus_src_alpha" => BlendFactor::OneMinusSrcAlpha,
+        "gl_dst_alpha" => BlendFactor::DstAlpha,
+        "gl_one_minus_dst_alpha" => BlendFactor::OneMinusDstAlpha,
+        "gl_src_alpha_saturate" => BlendFactor::SrcAlphaSaturate,
+        s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+    } )
+}
+
+fn parse_stage( tok : &mut Tokenizer, name : &str ) -> Result<Stage, ShaderError>
+{
+    let mut stage = Stage::default();
+    loop {
+        let t = match tok.next_token( true ) {
+            Some( t ) => t,
+            None => return Err( ShaderError::UnexpectedEnd { shader: name.to_string() } )
+        };
+        let line = t.line;
+        match t.text.to_lowercase().as_str() {
+            "}" => return Ok( stage ),
+            "map" => {
+                let t = tok.next_token( false );
+                stage.map = Some( match t {
+                    Some( ref t ) if t.text.eq_ignore_ascii_case( "$lightmap" ) => StageMap::Lightmap,
+                    Some( t ) => StageMap::Map( t.text.to_string() ),
+                    None => return Err( missing( line ) )
+                } );
+            },
+            "clampmap" => match tok.next_token( false ) {
+                Some( t ) => stage.map = Some( StageMap::ClampMap( t.text.to_string() ) ),
+                None => return Err( missing( line ) )
+            },
+            "videomap" => match tok.next_token( false ) {
+                Some( t ) => stage.map = Some( StageMap::VideoMap( t.text.to_string() ) ),
+                None => return Err( missing( line ) )
+            },
+            "animmap" => {
+                let frequency = float( tok, line )?;
+                let mut images = vec![];
+                // MAX_IMAGE_ANIMATIONS; the engine drops the rest
+                while let Some( t ) = tok.next_token( false ) {
+                    if images.len() < 8 {
+                        images.push( t.text.to_string() );
+                    }
+                }
+                if images.is_empty() {
+                    return Err( missing( line ) );
+                }
+                stage.map = Some( StageMap::AnimMap { frequency, images } );
+            },
+            "alphafunc" => {
+                stage.alpha_func = Some( match arg( tok, line )?.as_str() {
+                    "gt0" => AlphaFunc::Gt0,
+                    "lt128" => AlphaFunc::Lt128,
+                    "ge128" => AlphaFunc::Ge128,
+                    s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+                } );
+            },
+            "depthfunc" => {
+                stage.depth_func = Some( match arg( tok, line )?.as_str() {
+                    "lequal" => DepthFunc::LessEqual,
+                    "equal" => DepthFunc::Equal,
+                    s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+                } );
+            },
+            "depthwrite" => stage.depth_write = true,
+            "detail" => stage.detail = true,
+            "blendfunc" => {
+                let src = arg( tok, line )?;
+                stage.blend_func = Some( match src.as_str() {
+                    "add" => ( BlendFactor::One, BlendFactor::One ),
+                    "filter" => ( BlendFactor::DstColor, BlendFactor::Zero ),
+                    "blend" => ( BlendFactor::SrcAlpha, BlendFactor::OneMinusSrcAlpha ),
+                    _ => ( blend_factor( &src, line )?, blend_factor( &arg( tok, line )?, line )? )
+                } );
+            },
+            "rgbgen" => {
+                stage.rgb_gen = Some( match arg( tok, line )?.as_str() {
+                    "wave" => RgbGen::Wave( wave( tok, line )? ),
+                    "const" => RgbGen::Const( vector( tok, line )? ),
+                    "identity" => RgbGen::Identity,
+                    "identitylighting" => RgbGen::IdentityLighting,
+                    "entity" => RgbGen::Entity,
+                    "oneminusentity" => RgbGen::OneMinusEntity,
+                    "vertex" => RgbGen::Vertex,
+                    "exactvertex" => RgbGen::ExactVertex,
+                    "oneminusvertex" => RgbGen::OneMinusVertex,
+                    "lightingdiffuse" => RgbGen::LightingDiffuse,
+                    s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+                } );
+            },
+            "alphagen" => {
+                stage.alpha_gen = Some( match arg( tok, line )?.as_str() {
+                    "wave" => AlphaGen::Wave( wave( tok, line )? ),
+                    "const" => AlphaGen::Const( float( tok, line )? ),
+                    "identity" => AlphaGen::Identity,
+                    "entity" => AlphaGen::Entity,
+                    "oneminusentity" => AlphaGen::OneMinusEntity,
+                    "vertex" => AlphaGen::Vertex,
+                    "oneminusvertex" => AlphaGen::OneMinusVertex,
+                    "lightingspecular" => AlphaGen::LightingSpecular,
+                    "portal" => AlphaGen::Portal( float( tok, line )? ),
+                    s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+                } );
+            },
+            "tcgen" | "texgen" => {
+                stage.tc_gen = Some( match arg( tok, line )?.as_str() {
+                    "texture" | "base" => TcGen::Texture,
+                    "lightmap" => TcGen::Lightmap,
+                    "environment" => TcGen::Environment,
+                    "vector" => TcGen::Vector( vector( tok, line )?, vector( tok, line )? ),
+                    s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+                } );
+            },
+            "tcmod" => {
+                let tc_mod = parse_tc_mod( tok, line )?;
+                stage.tc_mods.push( tc_mod );
+            },
+            _ => skip_rest_of_line( tok )
+        }
+    }
+}
+
+fn parse_tc_mod( tok : &mut Tokenizer, line : usize ) -> Result<TcMod, ShaderError>
+{
+    Ok( match arg( tok, line )?.as_str() {
+        "turb" => TcMod::Turb {
+            base: float( tok, line )?, amplitude: float( tok, line )?,
+            phase: float( tok, line )?, frequency: float( tok, line )?
+        },
+        "scale" => TcMod::Scale( [float( tok, line )?, float( tok, line )?] ),
+        "scroll" => TcMod::Scroll( [float( tok, line )?, float( tok, line )?] ),
+        "stretch" => TcMod::Stretch( wave( tok, line )? ),
+        "transform" => TcMod::Transform {
+            matrix: [[float( tok, line )?, float( tok, line )?], [float( tok, line )?, float( tok, line )?]],
+            translate: [float( tok, line )?, float( tok, line )?]
+        },
+        "rotate" => TcMod::Rotate( float( tok, line )? ),
+        "entitytranslate" => TcMod::EntityTranslate,
+        s => return Err( ShaderError::BadValue { line, token: s.to_string() } )
+    } )
+}
@@ -0,0 +1,87 @@
+// Quake 3 .skin files.
+//
+// Each line binds a surface of an md3 to a shader, overriding the shader
+// names stored in the model:
+//
+//     h_head,models/players/sarge/band.tga
+//     tag_head,
+//
+// Tag lines carry no shader and are ignored, like the engine does.
+
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use md3::{Md3Model, Md3Surface};
+
+pub struct SkinSurface
+{
+    /// Surface name, lowercased
+    pub name : String,
+    pub shader : String,
+}
+
+pub struct Skin
+{
+    pub surfaces : Vec<SkinSurface>,
+}
+
+impl Skin
+{
+    pub fn load( fname : String ) -> io::Result<Skin>
+    {
+        let mut text = String::new();
+        File::open( fname )?.read_to_string( &mut text )?;
+        Ok( Skin::parse( &text ) )
+    }
+
+    pub fn parse( text : &str ) -> Skin
+    {
+        let mut skin = Skin { surfaces: vec![] };
+        for line in text.lines() {
+            let line = match line.find( "//" ) {
+                Some( i ) => &line[..i],
+                None => line
+            };
+            let mut parts = line.splitn( 2, ',' );
+            let name = parts.next().unwrap_or( "" ).trim().trim_matches( '"' );
+            let shader = parts.next().unwrap_or( "" ).trim().trim_matches( '"' );
+            if name.is_empty() || shader.is_empty() || name.contains( "tag_" ) {
+                continue;
+            }
+            skin.surfaces.push( SkinSurface {
+                name: name.to_lowercase(),
+                shader: shader.to_string(),
+            } );
+        }
+        skin
+    }
+
+    /// Shader bound to the surface called `surface_name`, compared case-insensitively.
+    pub fn shader_for( &self, surface_name : &str ) -> Option<&str>
+    {
+        let name = surface_name.to_lowercase();
+        self.surfaces.iter().find(| s | s.name == name ).map(| s | s.shader.as_str() )
+    }
+}
+
+impl Md3Surface
+{
+    /// Final shader name for this surface: the skin's binding if there is one,
+    /// otherwise the first shader stored in the md3.
+    pub fn resolve_shader<'a>( &'a self, skin : Option<&'a Skin> ) -> Option<&'a str>
+    {
+        if let Some( s ) = skin.and_then(| skin | skin.shader_for( self.header.name_str() ) ) {
+            return Some( s );
+        }
+        self.data.shaders.first().map(| s | s.name_str() ).filter(| s | !s.is_empty() )
+    }
+}
+
+impl Md3Model
+{
+    /// `Md3Surface::resolve_shader` for every surface, in surface order.
+    pub fn resolve_shaders<'a>( &'a self, skin : Option<&'a Skin> ) -> Vec<Option<&'a str>>
+    {
+        self.surfaces.iter().map(| s | s.resolve_shader( skin ) ).collect()
+    }
+}
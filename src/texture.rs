@@ -0,0 +1,123 @@
+// Finding the image files behind an MD3 shader name.
+//
+// The renderer first strips the extension and looks the name up among the
+// shader scripts; a script shader draws its stage images. Without a script
+// the name itself is the image. Either way an image that is missing under
+// its own extension is retried with the other formats, so a `.tga`
+// reference is satisfied by the `.jpg` that actually shipped.
+
+use std::io::prelude::*;
+use md3::Md3Model;
+use pk3::Pk3;
+use shader::ShaderSet;
+use skin::Skin;
+use vfs::Vfs;
+
+/// Image formats in the order the renderer tries them.
+pub const IMAGE_EXTENSIONS : [&str; 6] = [".tga", ".jpg", ".jpeg", ".png", ".pcx", ".bmp"];
+
+/// Anything that can say whether a game path exists.
+pub trait FileSource
+{
+    fn contains( &self, name : &str ) -> bool;
+}
+
+impl FileSource for Vfs
+{
+    fn contains( &self, name : &str ) -> bool
+    {
+        Vfs::contains( self, name )
+    }
+}
+
+impl<R : Read + Seek> FileSource for Pk3<R>
+{
+    fn contains( &self, name : &str ) -> bool
+    {
+        Pk3::contains( self, name )
+    }
+}
+
+/// `name` without the extension of its last path component.
+fn strip_extension( name : &str ) -> &str
+{
+    let slash = name.rfind( ['/', '\\'] ).map_or( 0, | i | i + 1 );
+    match name[slash ..].rfind( '.' ) {
+        Some( dot ) => &name[.. slash + dot],
+        None => name
+    }
+}
+
+/// The file that loading the image `name` would open: `name` itself if it
+/// exists, otherwise the first of `IMAGE_EXTENSIONS` that does.
+pub fn find_image<S : FileSource + ?Sized>( source : &S, name : &str ) -> Option<String>
+{
+    if name.is_empty() {
+        return None;
+    }
+    if source.contains( name ) {
+        return Some( name.to_string() );
+    }
+    let base = strip_extension( name );
+    IMAGE_EXTENSIONS.iter()
+        .map(| ext | format!( "{}{}", base, ext ) )
+        .find(| candidate | source.contains( candidate ) )
+}
+
+/// Outcome of resolving one shader name.
+#[derive(Clone, Debug, PartialEq)]
+pub struct TextureResolution
+{
+    /// The name as referenced by the md3 or skin
+    pub name : String,
+    /// Name of the script shader that defines it, if any
+    pub shader : Option<String>,
+    /// Image files found, in stage order
+    pub images : Vec<String>,
+    /// Image references that matched no file under any extension
+    pub unresolved : Vec<String>,
+}
+
+impl TextureResolution
+{
+    pub fn is_resolved( &self ) -> bool
+    {
+        self.unresolved.is_empty()
+    }
+}
+
+/// Resolves a shader name such as an `Md3Shader::name_str()` through the
+/// scripts in `shaders` and the files in `source`. Engine images such as
+/// `$whiteimage` are taken as present.
+pub fn resolve_texture<S : FileSource + ?Sized>( source : &S, shaders : &ShaderSet, name : &str ) -> TextureResolution
+{
+    let mut res = TextureResolution { name: name.to_string(), shader: None, images: vec![], unresolved: vec![] };
+    let refs = match shaders.get( name ) {
+        Some( shader ) => {
+            res.shader = Some( shader.name.clone() );
+            shader.images().into_iter().filter(| image | !image.starts_with( '$' ) ).collect()
+        },
+        None => vec![name]
+    };
+    for image in refs {
+        match find_image( source, image ) {
+            Some( found ) => res.images.push( found ),
+            None => res.unresolved.push( image.to_string() )
+        }
+    }
+    res
+}
+
+impl Md3Model
+{
+    /// `resolve_texture` for the shader of every surface, in surface order,
+    /// with `skin` overriding the shaders stored in the model. Surfaces with
+    /// no shader at all resolve an empty name, which is reported unresolved.
+    pub fn resolve_textures<S : FileSource + ?Sized>( &self, skin : Option<&Skin>, shaders : &ShaderSet,
+                                                    source : &S ) -> Vec<TextureResolution>
+    {
+        self.resolve_shaders( skin ).into_iter()
+            .map(| name | resolve_texture( source, shaders, name.unwrap_or( "" ) ) )
+            .collect()
+    }
+}
@@ -0,0 +1,320 @@
+// Sanity checks for MD3 models, for asset pipelines that want to reject
+// broken files before the engine sees them.
+//
+// The loader only refuses what it cannot read; `validate` also reports
+// what reads fine but breaks at runtime. Errors are things the engine
+// refuses, crashes on or reads out of bounds for; warnings load but render
+// wrong, such as degenerate triangles or frame bounds that make the model
+// get culled while still on screen.
+
+use std::fmt;
+use math::Vec3;
+use md3::{Md3Model, Md3Surface, MAX_QPATH, MD3_IDENT, MD3_MAX_FRAMES, MD3_MAX_SHADERS,
+          MD3_MAX_SURFACES, MD3_MAX_TAGS, MD3_MAX_TRIANGLES, MD3_MAX_VERTS, MD3_VERSION, MD3_XYZ_SCALE};
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Severity
+{
+    Warning,
+    Error,
+}
+
+/// The structure a diagnostic is about, by index.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Location
+{
+    Header,
+    Frame( usize ),
+    Tag { frame : usize, tag : usize },
+    Surface( usize ),
+    Shader { surface : usize, shader : usize },
+    Triangle { surface : usize, triangle : usize },
+    St { surface : usize, vertex : usize },
+    /// The vertices of one surface in one frame
+    SurfaceFrame { surface : usize, frame : usize },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Issue
+{
+    BadIdent,
+    BadVersion( i32 ),
+    NoFrames,
+    /// A count is over the engine limit
+    TooMany { what : &'static str, count : usize, max : usize },
+    /// A header count disagrees with the data actually present
+    CountMismatch { what : &'static str, expected : usize, found : usize },
+    /// A surface declares a different frame count than the model
+    FrameCountMismatch { surface : usize, model : usize },
+    /// A fixed-size name field has no terminating NUL
+    UnterminatedName,
+    NoShader,
+    NonFinite { what : &'static str },
+    IndexOutOfRange { index : i32, vertex_count : usize },
+    /// Repeated indices or zero area in the first frame
+    DegenerateTriangle,
+    /// Frame bounds with min above max on some axis
+    InvertedBounds,
+    /// `count` vertices lie outside the frame bounds, the first being `first`
+    OutsideBounds { count : usize, first : usize },
+    /// `count` vertices lie further from the local origin than the frame radius
+    OutsideRadius { count : usize, first : usize },
+}
+
+impl fmt::Display for Issue
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            Issue::BadIdent => write!( f, "ident is not IDP3" ),
+            Issue::BadVersion( v ) => write!( f, "version {} is not {}", v, MD3_VERSION ),
+            Issue::NoFrames => write!( f, "model has no frames" ),
+            Issue::TooMany { what, count, max } => write!( f, "{} {} exceeds the MD3 limit of {}", count, what, max ),
+            Issue::CountMismatch { what, expected, found } =>
+                write!( f, "header declares {} {} but {} are present", expected, what, found ),
+            Issue::FrameCountMismatch { surface, model } =>
+                write!( f, "surface has {} frames, model has {}", surface, model ),
+            Issue::UnterminatedName => write!( f, "name is not NUL terminated" ),
+            Issue::NoShader => write!( f, "surface has no shader" ),
+            Issue::NonFinite { what } => write!( f, "{} is NaN or infinite", what ),
+            Issue::IndexOutOfRange { index, vertex_count } =>
+                write!( f, "index {} out of range for {} vertices", index, vertex_count ),
+            Issue::DegenerateTriangle => write!( f, "degenerate triangle" ),
+            Issue::InvertedBounds => write!( f, "bounds minimum exceeds maximum" ),
+            Issue::OutsideBounds { count, first } =>
+                write!( f, "{} vertices outside frame bounds, first is vertex {}", count, first ),
+            Issue::OutsideRadius { count, first } =>
+                write!( f, "{} vertices outside frame radius, first is vertex {}", count, first ),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostic
+{
+    pub severity : Severity,
+    pub location : Location,
+    pub issue : Issue,
+}
+
+impl fmt::Display for Diagnostic
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        let severity = match self.severity {
+            Severity::Warning => "warning",
+            Severity::Error => "error",
+        };
+        write!( f, "{}: ", severity )?;
+        match self.location {
+            Location::Header => write!( f, "header" )?,
+            Location::Frame( i ) => write!( f, "frame {}", i )?,
+            Location::Tag { frame, tag } => write!( f, "frame {} tag {}", frame, tag )?,
+            Location::Surface( i ) => write!( f, "surface {}", i )?,
+            Location::Shader { surface, shader } => write!( f, "surface {} shader {}", surface, shader )?,
+            Location::Triangle { surface, triangle } => write!( f, "surface {} triangle {}", surface, triangle )?,
+            Location::St { surface, vertex } => write!( f, "surface {} st {}", surface, vertex )?,
+            Location::SurfaceFrame { surface, frame } => write!( f, "surface {} frame {}", surface, frame )?,
+        }
+        write!( f, ": {}", self.issue )
+    }
+}
+
+fn is_terminated( name : &[u8] ) -> bool
+{
+    name.len() < MAX_QPATH || name.contains( &0 )
+}
+
+fn vec_is_finite( v : &Vec3 ) -> bool
+{
+    v.x.is_finite() && v.y.is_finite() && v.z.is_finite()
+}
+
+struct Report
+{
+    diagnostics : Vec<Diagnostic>,
+}
+
+impl Report
+{
+    fn error( &mut self, location : Location, issue : Issue )
+    {
+        self.diagnostics.push( Diagnostic { severity: Severity::Error, location, issue } );
+    }
+
+    fn warning( &mut self, location : Location, issue : Issue )
+    {
+        self.diagnostics.push( Diagnostic { severity: Severity::Warning, location, issue } );
+    }
+
+    fn limit( &mut self, location : Location, what : &'static str, count : usize, max : usize )
+    {
+        if count > max {
+            self.error( location, Issue::TooMany { what, count, max } );
+        }
+    }
+
+    fn count( &mut self, location : Location, what : &'static str, expected : i64, found : usize )
+    {
+        if expected != found as i64 {
+            self.error( location, Issue::CountMismatch { what, expected: expected.max( 0 ) as usize, found } );
+        }
+    }
+}
+
+impl Md3Model
+{
+    /// Checks every structure of the model against the format and the
+    /// engine limits. An empty result means the model is clean.
+    pub fn validate( &self ) -> Vec<Diagnostic>
+    {
+        let mut r = Report { diagnostics: vec![] };
+        let h = &self.header;
+        let frame_count = h.frame_count.max( 0 ) as usize;
+
+        if h.ident != i32::from_le_bytes( MD3_IDENT ) {
+            r.error( Location::Header, Issue::BadIdent );
+        }
+        if h.version != MD3_VERSION {
+            r.error( Location::Header, Issue::BadVersion( h.version ) );
+        }
+        if !is_terminated( &h.name ) {
+            r.error( Location::Header, Issue::UnterminatedName );
+        }
+        if frame_count == 0 {
+            r.error( Location::Header, Issue::NoFrames );
+        }
+        r.limit( Location::Header, "frames", frame_count, MD3_MAX_FRAMES );
+        r.limit( Location::Header, "tags", h.tags_count.max( 0 ) as usize, MD3_MAX_TAGS );
+        r.limit( Location::Header, "surfaces", h.surface_count.max( 0 ) as usize, MD3_MAX_SURFACES );
+        r.count( Location::Header, "frames", h.frame_count as i64, self.frames.len() );
+        r.count( Location::Header, "tags", h.frame_count as i64 * h.tags_count as i64, self.tags.len() );
+        r.count( Location::Header, "surfaces", h.surface_count as i64, self.surfaces.len() );
+
+        for ( i, frame ) in self.frames.iter().enumerate() {
+            let loc = Location::Frame( i );
+            if !frame.name.contains( &0 ) {
+                r.error( loc, Issue::UnterminatedName );
+            }
+            let ( min, max ) = ( &frame.bounds[0], &frame.bounds[1] );
+            if !vec_is_finite( min ) || !vec_is_finite( max ) {
+                r.error( loc, Issue::NonFinite { what: "bounds" } );
+            } else if min.x > max.x || min.y > max.y || min.z > max.z {
+                r.error( loc, Issue::InvertedBounds );
+            }
+            if !vec_is_finite( &frame.local_origin ) {
+                r.error( loc, Issue::NonFinite { what: "local origin" } );
+            }
+            if !frame.radius.is_finite() {
+                r.error( loc, Issue::NonFinite { what: "radius" } );
+            }
+        }
+
+        let tags_count = h.tags_count.max( 1 ) as usize;
+        for ( i, tag ) in self.tags.iter().enumerate() {
+            let loc = Location::Tag { frame: i / tags_count, tag: i % tags_count };
+            if !is_terminated( &tag.name ) {
+                r.error( loc, Issue::UnterminatedName );
+            }
+            if !vec_is_finite( &tag.origin ) || !tag.axis.iter().all( vec_is_finite ) {
+                r.error( loc, Issue::NonFinite { what: "tag" } );
+            }
+        }
+
+        for ( i, surf ) in self.surfaces.iter().enumerate() {
+            self.validate_surface( i, surf, &mut r );
+        }
+        r.diagnostics
+    }
+
+    fn validate_surface( &self, index : usize, surf : &Md3Surface, r : &mut Report )
+    {
+        let h = &surf.header;
+        let loc = Location::Surface( index );
+        let vertex_count = h.vertex_count.max( 0 ) as usize;
+
+        if h.ident != i32::from_le_bytes( MD3_IDENT ) {
+            r.error( loc, Issue::BadIdent );
+        }
+        if !is_terminated( &h.name ) {
+            r.error( loc, Issue::UnterminatedName );
+        }
+        if h.frame_count != self.header.frame_count {
+            r.error( loc, Issue::FrameCountMismatch {
+                surface: h.frame_count.max( 0 ) as usize, model: self.header.frame_count.max( 0 ) as usize
+            } );
+        }
+        r.limit( loc, "shaders", h.shader_count.max( 0 ) as usize, MD3_MAX_SHADERS );
+        r.limit( loc, "vertices", vertex_count, MD3_MAX_VERTS );
+        r.limit( loc, "triangles", h.triangle_count.max( 0 ) as usize, MD3_MAX_TRIANGLES );
+        r.count( loc, "shaders", h.shader_count as i64, surf.data.shaders.len() );
+        r.count( loc, "triangles", h.triangle_count as i64, surf.data.triangles.len() );
+        r.count( loc, "st coordinates", h.vertex_count as i64, surf.data.st_data.len() );
+        r.count( loc, "vertices", h.vertex_count as i64 * h.frame_count as i64, surf.data.xyz_normals.len() );
+        if surf.data.shaders.is_empty() {
+            r.warning( loc, Issue::NoShader );
+        }
+
+        for ( i, shader ) in surf.data.shaders.iter().enumerate() {
+            if !is_terminated( &shader.name ) {
+                r.error( Location::Shader { surface: index, shader: i }, Issue::UnterminatedName );
+            }
+        }
+
+        for ( i, st ) in surf.data.st_data.iter().enumerate() {
+            if !st.st[0].is_finite() || !st.st[1].is_finite() {
+                r.error( Location::St { surface: index, vertex: i }, Issue::NonFinite { what: "st" } );
+            }
+        }
+
+        let first_frame = surf.frame_vertices( 0 );
+        for ( i, tri ) in surf.data.triangles.iter().enumerate() {
+            let loc = Location::Triangle { surface: index, triangle: i };
+            let bad = tri.indices.iter().find(| &&v | v < 0 || v as usize >= vertex_count );
+            if let Some( &v ) = bad {
+                r.error( loc, Issue::IndexOutOfRange { index: v, vertex_count } );
+                continue;
+            }
+            let [a, b, c] = tri.indices;
+            if a == b || b == c || a == c {
+                r.warning( loc, Issue::DegenerateTriangle );
+                continue;
+            }
+            if first_frame.len() == vertex_count {
+                let p = | v : i32 | {
+                    let xyz = first_frame[v as usize].xyz;
+                    Vec3::new( xyz[0] as f32, xyz[1] as f32, xyz[2] as f32 )
+                };
+                let n = ( p( b ) - p( a ) ).cross( p( c ) - p( a ) );
+                if n.x == 0.0 && n.y == 0.0 && n.z == 0.0 {
+                    r.warning( loc, Issue::DegenerateTriangle );
+                }
+            }
+        }
+
+        // One diagnostic per surface and frame; vertices are in the thousands
+        let slack = Vec3::new( MD3_XYZ_SCALE, MD3_XYZ_SCALE, MD3_XYZ_SCALE );
+        for ( f, frame ) in self.frames.iter().enumerate() {
+            let ( min, max ) = ( frame.bounds[0] - slack, frame.bounds[1] + slack );
+            let ( mut outside, mut outside_radius ) = ( ( 0, None ), ( 0, None ) );
+            for ( v, xyzn ) in surf.frame_vertices( f ).iter().enumerate() {
+                let p = Vec3::from_array( xyzn.decode_xyz() );
+                if p.x < min.x || p.y < min.y || p.z < min.z || p.x > max.x || p.y > max.y || p.z > max.z {
+                    outside.0 += 1;
+                    outside.1 = outside.1.or( Some( v ) );
+                }
+                if ( p - frame.local_origin ).length() > frame.radius + MD3_XYZ_SCALE {
+                    outside_radius.0 += 1;
+                    outside_radius.1 = outside_radius.1.or( Some( v ) );
+                }
+            }
+            let loc = Location::SurfaceFrame { surface: index, frame: f };
+            if let ( count, Some( first ) ) = outside {
+                r.warning( loc, Issue::OutsideBounds { count, first } );
+            }
+            if let ( count, Some( first ) ) = outside_radius {
+                r.warning( loc, Issue::OutsideRadius { count, first } );
+            }
+        }
+    }
+}
@@ -0,0 +1,242 @@
+// A layered view over game directories and pk3 archives, resolving
+// `models/players/sarge/upper.md3` the way the engine's filesystem does.
+//
+// Search order follows FS_AddGameDirectory in the 1.32 engine: a game
+// directory adds its loose files first and then its pk3s sorted by name
+// (case-insensitively), each new layer going in front of the others. So a
+// later pk3 overrides an earlier one, any pk3 overrides loose files in the
+// same directory, and a later game directory (a mod over baseq3) overrides
+// everything added before it.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::fs::File;
+use std::io;
+use std::io::prelude::*;
+use std::path::{Path, PathBuf};
+use animation::{AnimationError, AnimationSet};
+use md3::{Md3Error, Md3Model};
+use pk3::{normalize_path, Pk3, Pk3Error};
+use skin::Skin;
+
+#[derive(Debug)]
+pub enum VfsError
+{
+    Io( io::Error ),
+    /// No layer has the file
+    NotFound( String ),
+    /// A pk3 could not be opened or read
+    Pk3 { pk3 : PathBuf, error : Pk3Error },
+    Md3 { name : String, error : Md3Error },
+    Animation { name : String, error : AnimationError },
+}
+
+impl fmt::Display for VfsError
+{
+    fn fmt( &self, f : &mut fmt::Formatter ) -> fmt::Result
+    {
+        match *self {
+            VfsError::Io( ref e ) => write!( f, "I/O error: {}", e ),
+            VfsError::NotFound( ref name ) => write!( f, "{} not found", name ),
+            VfsError::Pk3 { ref pk3, ref error } => write!( f, "{}: {}", pk3.display(), error ),
+            VfsError::Md3 { ref name, ref error } => write!( f, "{}: {}", name, error ),
+            VfsError::Animation { ref name, ref error } => write!( f, "{}: {}", name, error ),
+        }
+    }
+}
+
+impl Error for VfsError
+{
+    fn source( &self ) -> Option<&(dyn Error + 'static)>
+    {
+        match *self {
+            VfsError::Io( ref e ) => Some( e ),
+            VfsError::Pk3 { ref error, .. } => Some( error ),
+            VfsError::Md3 { ref error, .. } => Some( error ),
+            VfsError::Animation { ref error, .. } => Some( error ),
+            _ => None
+        }
+    }
+}
+
+impl From<io::Error> for VfsError
+{
+    fn from( e : io::Error ) -> VfsError
+    {
+        VfsError::Io( e )
+    }
+}
+
+enum Layer
+{
+    Dir( PathBuf ),
+    Pk3( PathBuf, Pk3<File> ),
+}
+
+/// Rejects names that could escape a directory layer, like the engine's
+/// checks for ".." and "::".
+fn is_safe_path( name : &str ) -> bool
+{
+    !name.is_empty() && !name.contains( ".." ) && !name.contains( "::" )
+        && !name.starts_with( '/' ) && !name.starts_with( '\\' )
+}
+
+#[derive(Default)]
+pub struct Vfs
+{
+    /// In the order added; later layers win
+    layers : Vec<Layer>,
+}
+
+impl Vfs
+{
+    pub fn new() -> Vfs
+    {
+        Vfs { layers: vec![] }
+    }
+
+    /// Adds `dir` and then every .pk3 directly inside it, like a game
+    /// directory such as baseq3 or a mod.
+    pub fn add_game_dir( &mut self, dir : String ) -> Result<(), VfsError>
+    {
+        let mut pk3s = vec![];
+        for entry in fs::read_dir( &dir )? {
+            let path = entry?.path();
+            let is_pk3 = path.extension()
+                .is_some_and(| ext | ext.to_string_lossy().eq_ignore_ascii_case( "pk3" ) );
+            if is_pk3 && path.is_file() {
+                pk3s.push( path );
+            }
+        }
+        pk3s.sort_by_key(| p | p.file_name().unwrap_or_default().to_string_lossy().to_lowercase() );
+
+        self.add_dir( dir );
+        for path in pk3s {
+            self.add_pk3( path.to_string_lossy().into_owned() )?;
+        }
+        Ok( () )
+    }
+
+    /// Adds loose files under `dir` over everything added so far.
+    pub fn add_dir( &mut self, dir : String )
+    {
+        self.layers.push( Layer::Dir( PathBuf::from( dir ) ) );
+    }
+
+    /// Adds a single archive over everything added so far.
+    pub fn add_pk3( &mut self, fname : String ) -> Result<(), VfsError>
+    {
+        let path = PathBuf::from( fname );
+        let pk3 = File::open( &path ).map_err( Pk3Error::Io ).and_then( Pk3::from_reader )
+            .map_err(| error | VfsError::Pk3 { pk3: path.clone(), error } )?;
+        self.layers.push( Layer::Pk3( path, pk3 ) );
+        Ok( () )
+    }
+
+    pub fn contains( &self, name : &str ) -> bool
+    {
+        self.find( name ).is_some()
+    }
+
+    /// Where `name` resolves: the directory or pk3 that provides it.
+    pub fn source_of( &self, name : &str ) -> Option<&Path>
+    {
+        self.find( name ).map(| i | match self.layers[i] {
+            Layer::Dir( ref p ) | Layer::Pk3( ref p, _ ) => p.as_path()
+        })
+    }
+
+    fn find( &self, name : &str ) -> Option<usize>
+    {
+        if !is_safe_path( name ) {
+            return None;
+        }
+        ( 0 .. self.layers.len() ).rev().find(| &i | match self.layers[i] {
+            Layer::Dir( ref dir ) => dir.join( name ).is_file(),
+            Layer::Pk3( _, ref pk3 ) => pk3.contains( name ),
+        })
+    }
+
+    /// Contents of `name` from the highest priority layer that has it.
+    pub fn read( &mut self, name : &str ) -> Result<Vec<u8>, VfsError>
+    {
+        let i = match self.find( name ) {
+            Some( i ) => i,
+            None => return Err( VfsError::NotFound( name.to_string() ) )
+        };
+        match self.layers[i] {
+            Layer::Dir( ref dir ) => {
+                let mut bytes = vec![];
+                File::open( dir.join( name ) )?.read_to_end( &mut bytes )?;
+                Ok( bytes )
+            }
+            Layer::Pk3( ref path, ref mut pk3 ) =>
+                pk3.read( name ).map_err(| error | VfsError::Pk3 { pk3: path.clone(), error } )
+        }
+    }
+
+    pub fn read_to_string( &mut self, name : &str ) -> Result<String, VfsError>
+    {
+        let bytes = self.read( name )?;
+        Ok( String::from_utf8_lossy( &bytes ).into_owned() )
+    }
+
+    /// Files directly inside `dir` ending in `extension` (e.g. "scripts",
+    /// ".shader"), across all layers. Names are full paths, sorted and
+    /// listed once even when several layers provide them. A `dir` that
+    /// could escape a directory layer lists nothing.
+    pub fn list_files( &self, dir : &str, extension : &str ) -> Vec<String>
+    {
+        if !is_safe_path( dir ) {
+            return vec![];
+        }
+        let prefix = normalize_path( dir.trim_end_matches( '/' ) ) + "/";
+        let extension = normalize_path( extension );
+        let mut found = BTreeMap::new();
+        for layer in self.layers.iter() {
+            match *layer {
+                Layer::Dir( ref root ) => {
+                    let entries = match fs::read_dir( root.join( dir ) ) {
+                        Ok( entries ) => entries,
+                        Err( _ ) => continue
+                    };
+                    for entry in entries.flatten() {
+                        let name = format!( "{}/{}", dir.trim_end_matches( '/' ), entry.file_name().to_string_lossy() );
+                        if entry.path().is_file() && normalize_path( &name ).ends_with( &extension ) {
+                            found.insert( normalize_path( &name ), name );
+                        }
+                    }
+                }
+                Layer::Pk3( _, ref pk3 ) => {
+                    for name in pk3.entries() {
+                        let key = normalize_path( name );
+                        let in_dir = key.starts_with( &prefix ) && !key[prefix.len() ..].contains( '/' );
+                        if in_dir && key.ends_with( &extension ) {
+                            found.insert( key, name.clone() );
+                        }
+                    }
+                }
+            }
+        }
+        found.into_values().collect()
+    }
+
+    pub fn load_md3( &mut self, name : &str ) -> Result<Md3Model, VfsError>
+    {
+        let bytes = self.read( name )?;
+        Md3Model::from_bytes( &bytes ).map_err(| error | VfsError::Md3 { name: name.to_string(), error } )
+    }
+
+    pub fn load_skin( &mut self, name : &str ) -> Result<Skin, VfsError>
+    {
+        Ok( Skin::parse( &self.read_to_string( name )? ) )
+    }
+
+    pub fn load_animation( &mut self, name : &str ) -> Result<AnimationSet, VfsError>
+    {
+        AnimationSet::parse( &self.read_to_string( name )? )
+            .map_err(| error | VfsError::Animation { name: name.to_string(), error } )
+    }
+}
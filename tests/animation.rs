@@ -0,0 +1,126 @@
+extern crate md3_rs;
+
+use md3_rs::animation::{AnimationClip, AnimationError, AnimationId, AnimationSet, Footsteps, Sex};
+
+const SARGE_CFG : &str = "
+// animation config file
+
+sex m
+
+headoffset 0 0 -2
+
+footsteps boot
+
+0	30	0	25		// BOTH_DEATH1
+29	1	0	25		// BOTH_DEAD1
+30	30	0	25		// BOTH_DEATH2
+59	1	0	25		// BOTH_DEAD2
+60	30	0	25		// BOTH_DEATH3
+89	1	0	25		// BOTH_DEAD3
+
+90	40	0	20		// TORSO_GESTURE
+130	6	0	15		// TORSO_ATTACK
+136	6	0	15		// TORSO_ATTACK2
+142	5	0	20		// TORSO_DROP
+147	4	0	20		// TORSO_RAISE
+151	1	0	15		// TORSO_STAND
+152	1	0	15		// TORSO_STAND2
+
+153	8	8	20		// LEGS_WALKCR
+161	12	12	20		// LEGS_WALK
+173	9	9	18		// LEGS_RUN
+182	-10	10	20		// LEGS_BACK
+192	10	10	15		// LEGS_SWIM
+202	8	0	15		// LEGS_JUMP
+210	1	0	15		// LEGS_LAND
+211	8	0	15		// LEGS_JUMPB
+219	1	0	15		// LEGS_LANDB
+220	10	10	15		// LEGS_IDLE
+230	10	10	15		// LEGS_IDLECR
+240	7	7	15		// LEGS_TURN
+";
+
+#[test]
+fn parses_keywords_and_rebases_legs_frames()
+{
+    let set = AnimationSet::parse( SARGE_CFG ).unwrap();
+    assert_eq!( set.sex, Sex::Male );
+    assert_eq!( set.footsteps, Footsteps::Boot );
+    assert_eq!( set.head_offset.z, -2.0 );
+
+    assert_eq!( set.clip( AnimationId::TorsoGesture ).first_frame, 90 );
+    assert_eq!( set.clip( AnimationId::LegsWalkCr ).first_frame, 90 );
+    assert_eq!( set.clip( AnimationId::LegsRun ).first_frame, 110 );
+    assert_eq!( set.clip( AnimationId::LegsRun ).fps, 18.0 );
+
+    let back = set.clip( AnimationId::LegsBack );
+    assert!( back.reversed );
+    assert_eq!( back.num_frames, 10 );
+
+    // Team Arena animations are optional and fall back to the gesture
+    assert_eq!( set.clip( AnimationId::TorsoNegative ), set.clip( AnimationId::TorsoGesture ) );
+}
+
+#[test]
+fn clip_frames_loop_and_hold()
+{
+    let set = AnimationSet::parse( SARGE_CFG ).unwrap();
+
+    let run = set.clip( AnimationId::LegsRun );
+    assert_eq!( run.frame_index( 0 ), 110 );
+    assert_eq!( run.frame_index( 9 ), 110 );
+    let ( a, b, t ) = run.frame_at( 0.5 / 18.0 );
+    assert_eq!( ( a, b ), ( 110, 111 ) );
+    assert!( ( t - 0.5 ).abs() < 1e-4 );
+    // Far past the end a looping clip still lands inside the loop
+    for &time in [ 1e30, f32::INFINITY ].iter() {
+        let ( a, b, t ) = run.frame_at( time );
+        assert!( a >= 110 && a < 110 + run.num_frames as u32 && b >= 110 && b < 110 + run.num_frames as u32 );
+        assert!( ( 0.0 .. 1.0 ).contains( &t ) );
+    }
+
+    let jump = set.clip( AnimationId::LegsJump );
+    assert_eq!( jump.frame_index( 100 ), jump.first_frame as u32 + 7 );
+}
+
+#[test]
+fn missing_animations_is_an_error()
+{
+    match AnimationSet::parse( "sex f\n0 30 0 25\n" ) {
+        Err( AnimationError::MissingAnimations { found: 1 } ) => {},
+        _ => panic!( "expected MissingAnimations" )
+    }
+}
+
+#[test]
+fn non_ascii_comments_are_skipped()
+{
+    let cfg = format!( "/* © id software, \u{fffd} */\n// größe\n{}", SARGE_CFG );
+    let set = AnimationSet::parse( &cfg ).unwrap();
+    assert_eq!( set.sex, Sex::Male );
+    assert_eq!( set.clip( AnimationId::LegsRun ).first_frame, 110 );
+}
+
+#[test]
+fn empty_quoted_keyword_does_not_panic()
+{
+    // "" is an unknown keyword; the frame numbers that follow are too few
+    match AnimationSet::parse( "\"\" 0 1 0 1\n" ) {
+        Err( AnimationError::MissingAnimations { found: 1 } ) => {},
+        _ => panic!( "expected MissingAnimations" )
+    }
+}
+
+#[test]
+fn reversed_clip_with_overlong_loop_stays_in_range()
+{
+    let clip = AnimationClip { first_frame: 10, num_frames: 2, looping_frames: 5, fps: 10.0, reversed: true };
+    for n in 0 .. 20 {
+        let f = clip.frame_index( n );
+        assert!( f == 10 || f == 11, "frame {} -> {}", n, f );
+    }
+
+    let cfg = SARGE_CFG.replace( "182\t-10\t10\t20", "182\t-10\t40\t20" );
+    let set = AnimationSet::parse( &cfg ).unwrap();
+    assert_eq!( set.clip( AnimationId::LegsBack ).looping_frames, 10 );
+}
@@ -0,0 +1,216 @@
+// Hand-built MD3 fixtures, written byte by byte so the loader is not
+// tested against its own writer.
+
+#![allow(dead_code)]
+
+extern crate zip;
+
+use byteorder::{LittleEndian, WriteBytesExt};
+use md3_rs::md3::Md3Model;
+use std::io::{Cursor, Write};
+
+pub struct SurfaceSpec
+{
+    pub name : &'static str,
+    pub shader : &'static str,
+    /// frame_count * vertex_count positions, grouped by frame
+    pub xyz : Vec<[i16; 3]>,
+    pub st : Vec<[f32; 2]>,
+    pub triangles : Vec<[i32; 3]>,
+}
+
+fn put_name( out : &mut Vec<u8>, name : &str, len : usize )
+{
+    let mut buf = vec![0u8; len];
+    buf[..name.len()].copy_from_slice( name.as_bytes() );
+    out.extend_from_slice( &buf );
+}
+
+fn put_i32s( out : &mut Vec<u8>, vals : &[i32] )
+{
+    for v in vals {
+        out.write_i32::<LittleEndian>( *v ).unwrap();
+    }
+}
+
+fn put_f32s( out : &mut Vec<u8>, vals : &[f32] )
+{
+    for v in vals {
+        out.write_f32::<LittleEndian>( *v ).unwrap();
+    }
+}
+
+fn build_surface( frame_count : i32, spec : &SurfaceSpec ) -> Vec<u8>
+{
+    let vertex_count = spec.xyz.len() as i32 / frame_count;
+    let shaders_offset = 108;
+    let triangles_offset = shaders_offset + 68;
+    let st_offset = triangles_offset + 12 * spec.triangles.len() as i32;
+    let xyzn_offset = st_offset + 8 * spec.st.len() as i32;
+    let end_offset = xyzn_offset + 8 * spec.xyz.len() as i32;
+
+    let mut out = vec![];
+    out.extend_from_slice( b"IDP3" );
+    put_name( &mut out, spec.name, 64 );
+    put_i32s( &mut out, &[
+        0, frame_count, 1, vertex_count, spec.triangles.len() as i32,
+        triangles_offset, shaders_offset, st_offset, xyzn_offset, end_offset
+    ] );
+    put_name( &mut out, spec.shader, 64 );
+    put_i32s( &mut out, &[0] );
+    for tri in spec.triangles.iter() {
+        put_i32s( &mut out, tri );
+    }
+    for st in spec.st.iter() {
+        put_f32s( &mut out, st );
+    }
+    for xyz in spec.xyz.iter() {
+        for c in xyz.iter() {
+            out.write_i16::<LittleEndian>( *c ).unwrap();
+        }
+        out.write_i16::<LittleEndian>( 0 ).unwrap();
+    }
+    assert_eq!( out.len() as i32, end_offset );
+    out
+}
+
+pub struct TagSpec
+{
+    pub name : &'static str,
+    pub origin : [f32; 3],
+    pub axis : [[f32; 3]; 3],
+}
+
+/// Builds a complete IDP3 file with `frame_count` frames, no tags and the given surfaces.
+pub fn build_md3( frame_count : i32, surfaces : &[SurfaceSpec] ) -> Vec<u8>
+{
+    build_md3_with_tags( frame_count, &[], surfaces )
+}
+
+/// Like `build_md3`, with `tags` holding the same number of tags for every frame, grouped by frame.
+pub fn build_md3_with_tags( frame_count : i32, tags : &[TagSpec], surfaces : &[SurfaceSpec] ) -> Vec<u8>
+{
+    let tags_count = tags.len() as i32 / frame_count;
+    let frames_offset = 108;
+    let tags_offset = frames_offset + 56 * frame_count;
+    let surfaces_offset = tags_offset + 112 * tags.len() as i32;
+    let surf_bytes : Vec<Vec<u8>> = surfaces.iter().map(| s | build_surface( frame_count, s ) ).collect();
+    let eof_offset = surfaces_offset + surf_bytes.iter().map(| b | b.len() as i32 ).sum::<i32>();
+
+    let mut out = vec![];
+    out.extend_from_slice( b"IDP3" );
+    put_i32s( &mut out, &[15] );
+    put_name( &mut out, "fixture", 64 );
+    put_i32s( &mut out, &[
+        0, frame_count, tags_count, surfaces.len() as i32, 0,
+        frames_offset, tags_offset, surfaces_offset, eof_offset
+    ] );
+    for i in 0 .. frame_count {
+        put_f32s( &mut out, &[-1.0, -1.0, -1.0, 1.0, 1.0, 1.0, 0.0, 0.0, 0.0, 2.0] );
+        put_name( &mut out, &format!( "frame{}", i ), 16 );
+    }
+    for tag in tags.iter() {
+        put_name( &mut out, tag.name, 64 );
+        put_f32s( &mut out, &tag.origin );
+        for axis in tag.axis.iter() {
+            put_f32s( &mut out, axis );
+        }
+    }
+    for b in surf_bytes {
+        out.extend_from_slice( &b );
+    }
+    assert_eq!( out.len() as i32, eof_offset );
+    out
+}
+
+/// A model with no surfaces, only `tags` over `frame_count` frames.
+pub fn tag_model( frame_count : i32, tags : &[TagSpec] ) -> Md3Model
+{
+    Md3Model::from_bytes( &build_md3_with_tags( frame_count, tags, &[] ) ).unwrap()
+}
+
+/// One frame of a single triangle, (0,0,0) (1,0,0) (0,1,0) in model
+/// units, with a UV per vertex.
+pub fn triangle( name : &'static str, shader : &'static str ) -> SurfaceSpec
+{
+    SurfaceSpec {
+        name, shader,
+        xyz: vec![[0, 0, 0], [64, 0, 0], [0, 64, 0]],
+        st: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+        triangles: vec![[0, 1, 2]],
+    }
+}
+
+/// `triangle` over two frames, the second raised one unit and doubled in size.
+pub fn animated_triangle( name : &'static str, shader : &'static str ) -> SurfaceSpec
+{
+    let mut spec = triangle( name, shader );
+    spec.xyz.extend_from_slice( &[[0, 0, 64], [128, 0, 64], [0, 128, 64]] );
+    spec
+}
+
+/// The smallest complete model: one frame, one `triangle` called "tri".
+pub fn triangle_md3() -> Vec<u8>
+{
+    build_md3( 1, &[ triangle( "tri", "tri.tga" ) ] )
+}
+
+/// Two surfaces over `frame_count` frames: "a", a triangle rising one unit
+/// per frame, and "b", a quad of two triangles sliding half a unit along X
+/// per frame, each with its own shader.
+pub fn two_surfaces( frame_count : i32 ) -> Vec<SurfaceSpec>
+{
+    let frames = frame_count as usize;
+    vec![
+        SurfaceSpec {
+            name: "a", shader: "textures/a.tga",
+            xyz: ( 0 .. frames ).flat_map(| f | {
+                let z = 64 * f as i16;
+                vec![[0, 0, z], [64, 0, z], [0, 64, z]]
+            }).collect(),
+            st: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 0.25]],
+            triangles: vec![[0, 1, 2]],
+        },
+        SurfaceSpec {
+            name: "b", shader: "textures/b.tga",
+            xyz: ( 0 .. frames ).flat_map(| f | {
+                let x = 128 + 32 * f as i16;
+                vec![[x, 0, 0], [x, 32, 0], [x, 0, 32], [x, 32, 32]]
+            }).collect(),
+            st: vec![[0.0, 0.0]; 4],
+            triangles: vec![[0, 1, 2], [2, 1, 3]],
+        },
+    ]
+}
+
+/// Two frames of a tetrahedron corner, four vertices and two triangles,
+/// mirrored through the origin in the second frame so both stay inside the
+/// +/-1 unit frame bounds `build_md3` writes.
+pub fn corner( name : &'static str, shader : &'static str ) -> SurfaceSpec
+{
+    SurfaceSpec {
+        name, shader,
+        xyz: vec![[0, 0, 0], [64, 0, 0], [0, 64, 0], [0, 0, 64],
+                  [0, 0, 0], [-64, 0, 0], [0, -64, 0], [0, 0, -64]],
+        st: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0], [1.0, 1.0]],
+        triangles: vec![[0, 1, 2], [0, 2, 3]],
+    }
+}
+
+/// A zip archive holding `entries` deflated, in the given order. Names
+/// ending in '/' become directory entries.
+pub fn build_pk3( entries : &[(&str, &[u8])] ) -> Vec<u8>
+{
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method( zip::CompressionMethod::Deflated );
+    let mut zip = zip::ZipWriter::new( Cursor::new( vec![] ) );
+    for &( name, data ) in entries.iter() {
+        if name.ends_with( '/' ) {
+            zip.add_directory( name, options ).unwrap();
+        } else {
+            zip.start_file( name, options ).unwrap();
+            zip.write_all( data ).unwrap();
+        }
+    }
+    zip.finish().unwrap().into_inner()
+}
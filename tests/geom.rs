@@ -0,0 +1,76 @@
+extern crate byteorder;
+#[macro_use]
+extern crate md3_rs;
+
+mod common;
+
+use common::build_md3;
+use md3_rs::geom::{CreateGLReadyMesh, GLReadyMesh};
+use md3_rs::md3::Md3Model;
+
+fn two_surfaces( frame_count : i32 ) -> Md3Model
+{
+    Md3Model::from_bytes( &build_md3( frame_count, &common::two_surfaces( frame_count ) ) ).unwrap()
+}
+
+#[test]
+fn animated_mesh_has_one_merged_mesh_per_frame()
+{
+    let mesh = match two_surfaces( 3 ).create_gl_ready_mesh() {
+        GLReadyMesh::VertexAnimated( m ) => m,
+        _ => panic!( "expected a vertex animated mesh" ),
+    };
+    assert_eq!( mesh.frames.len(), 3 );
+
+    for ( f, frame ) in mesh.frames.iter().enumerate() {
+        assert_eq!( frame.vertices.len(), 7 );
+        assert_eq!( frame.indices, vec![0, 1, 2, 3, 4, 5, 5, 4, 6] );
+        assert_eq!( frame.vertices[0].position, [0.0, 0.0, f as f32] );
+        assert_eq!( frame.vertices[3].position, [2.0 + 0.5 * f as f32, 0.0, 0.0] );
+
+        let ranges : Vec<(&str, usize, usize)> = frame.submeshes.iter()
+            .map(| s | ( s.shader.as_str(), s.first_index, s.index_count ))
+            .collect();
+        assert_eq!( ranges, vec![( "textures/a.tga", 0, 3 ), ( "textures/b.tga", 3, 6 )] );
+    }
+}
+
+#[test]
+fn static_mesh_includes_every_surface()
+{
+    let mesh = match two_surfaces( 1 ).create_gl_ready_mesh() {
+        GLReadyMesh::Static( m ) => m,
+        _ => panic!( "expected a static mesh" ),
+    };
+    assert_eq!( mesh.vertices.len(), 7 );
+    assert_eq!( mesh.indices.len(), 9 );
+    assert_eq!( mesh.submeshes[1].name, "b" );
+}
+
+#[test]
+fn out_of_range_index_is_corrupted()
+{
+    for &bad in [ -1, 4 ].iter() {
+        let mut m = two_surfaces( 1 );
+        m.surfaces[1].data.triangles[1].indices[2] = bad;
+        match m.create_gl_ready_mesh() {
+            GLReadyMesh::Corrupted( e ) => assert!( e.contains( "'b'" ), "{}", e ),
+            _ => panic!( "expected a corrupted mesh for index {}", bad ),
+        }
+    }
+}
+
+mod shim
+{
+    implement_geometry_types!();
+}
+
+#[test]
+fn compatibility_macro_reexports_the_geom_module()
+{
+    use shim::geom::CreateGLReadyMesh;
+    match two_surfaces( 2 ).create_gl_ready_mesh() {
+        shim::geom::GLReadyMesh::VertexAnimated( m ) => assert_eq!( m.frames.len(), 2 ),
+        _ => panic!( "expected a vertex animated mesh" ),
+    }
+}
@@ -0,0 +1,115 @@
+extern crate byteorder;
+extern crate md3_rs;
+extern crate serde_json;
+
+mod common;
+
+use common::{animated_triangle, build_md3, build_md3_with_tags, triangle, TagSpec};
+use md3_rs::gltf::Gltf;
+use md3_rs::math::Vec3;
+use md3_rs::md3::Md3Model;
+use std::io;
+
+fn animated_model() -> Md3Model
+{
+    let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let bytes = build_md3_with_tags( 2, &[
+        TagSpec { name: "tag_weapon", origin: [1.0, 0.0, 0.0], axis: identity },
+        TagSpec { name: "tag_weapon", origin: [2.0, 0.0, 0.0], axis: identity },
+    ], &[ animated_triangle( "body", "models/body.tga" ) ] );
+    Md3Model::from_bytes( &bytes ).unwrap()
+}
+
+#[test]
+fn maps_frames_to_morph_targets_and_tags_to_nodes()
+{
+    let g = Gltf::from_model( &animated_model(), None, 10.0 ).unwrap();
+    let doc = &g.json;
+
+    let prim = &doc["meshes"][0]["primitives"][0];
+    assert_eq!( prim["targets"].as_array().unwrap().len(), 1 );
+    assert_eq!( doc["materials"][0]["name"], "models/body.tga" );
+    assert_eq!( doc["images"][0]["uri"], "models/body.tga" );
+
+    let pos = &doc["accessors"][prim["attributes"]["POSITION"].as_u64().unwrap() as usize];
+    assert_eq!( pos["count"], 3 );
+    assert_eq!( pos["max"][0], 1.0 );
+
+    assert_eq!( doc["nodes"][1]["name"], "tag_weapon" );
+    assert_eq!( doc["nodes"][1]["translation"][0], 1.0 );
+    // weights on the mesh node plus translation and rotation on the tag
+    assert_eq!( doc["animations"][0]["channels"].as_array().unwrap().len(), 3 );
+    assert_eq!( doc["buffers"][0]["byteLength"].as_u64().unwrap() as usize, g.bin.len() );
+}
+
+#[test]
+fn glb_has_header_and_aligned_chunks()
+{
+    let g = Gltf::from_model( &animated_model(), None, 10.0 ).unwrap();
+    let mut glb = vec![];
+    g.write_glb( &mut glb ).unwrap();
+
+    assert_eq!( &glb[0..4], b"glTF" );
+    assert_eq!( glb[4], 2 );
+    let total = u32::from_le_bytes( [glb[8], glb[9], glb[10], glb[11]] ) as usize;
+    assert_eq!( total, glb.len() );
+    let json_len = u32::from_le_bytes( [glb[12], glb[13], glb[14], glb[15]] ) as usize;
+    assert_eq!( json_len % 4, 0 );
+    assert_eq!( &glb[16..20], b"JSON" );
+    assert_eq!( &glb[20 + json_len + 4 .. 20 + json_len + 8], b"BIN\0" );
+}
+
+/// The rows of a tightly packed accessor of 4-byte components.
+fn accessor_words( g : &Gltf, index : &serde_json::Value, width : usize ) -> Vec<Vec<[u8; 4]>>
+{
+    let acc = &g.json["accessors"][index.as_u64().unwrap() as usize];
+    let view = &g.json["bufferViews"][acc["bufferView"].as_u64().unwrap() as usize];
+    let start = view["byteOffset"].as_u64().unwrap() as usize;
+    ( 0 .. acc["count"].as_u64().unwrap() as usize )
+        .map(| i | ( 0 .. width ).map(| c | {
+            let at = start + ( i * width + c ) * 4;
+            [ g.bin[at], g.bin[at + 1], g.bin[at + 2], g.bin[at + 3] ]
+        }).collect() )
+        .collect()
+}
+
+#[test]
+fn triangles_are_rewound_to_face_along_their_normals()
+{
+    // Clockwise seen from +Z, the side the (zero-encoded) normals point to
+    let mut tri = triangle( "tri", "tri.tga" );
+    tri.triangles = vec![[0, 2, 1]];
+    let bytes = build_md3( 1, &[ tri ] );
+    let g = Gltf::from_model( &Md3Model::from_bytes( &bytes ).unwrap(), None, 10.0 ).unwrap();
+    let prim = &g.json["meshes"][0]["primitives"][0];
+    let vec3 = | row : &Vec<[u8; 4]> | Vec3::new( f32::from_le_bytes( row[0] ), f32::from_le_bytes( row[1] ),
+                                                 f32::from_le_bytes( row[2] ) );
+    let positions : Vec<Vec3> = accessor_words( &g, &prim["attributes"]["POSITION"], 3 ).iter().map( vec3 ).collect();
+    let normals : Vec<Vec3> = accessor_words( &g, &prim["attributes"]["NORMAL"], 3 ).iter().map( vec3 ).collect();
+    let indices : Vec<usize> = accessor_words( &g, &prim["indices"], 1 ).iter()
+        .map(| row | u32::from_le_bytes( row[0] ) as usize ).collect();
+
+    assert_eq!( indices, vec![0, 1, 2] );
+    // glTF fronts are counter-clockwise
+    let p : Vec<Vec3> = indices.iter().map(| &i | positions[i] ).collect();
+    let face = ( p[1] - p[0] ).cross( p[2] - p[0] );
+    for &i in indices.iter() {
+        assert!( face.dot( normals[i] ) > 0.0 );
+    }
+}
+
+#[test]
+fn rejects_bad_rates_and_out_of_range_indices()
+{
+    for &fps in [ 0.0, -10.0, f32::NAN, f32::INFINITY ].iter() {
+        let err = Gltf::from_model( &animated_model(), None, fps ).err().unwrap();
+        assert_eq!( err.kind(), io::ErrorKind::InvalidInput );
+    }
+
+    for &bad in [ -1, 3 ].iter() {
+        let mut m = animated_model();
+        m.surfaces[0].data.triangles[0].indices[1] = bad;
+        let err = Gltf::from_model( &m, None, 10.0 ).err().unwrap();
+        assert_eq!( err.kind(), io::ErrorKind::InvalidInput );
+    }
+}
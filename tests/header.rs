@@ -0,0 +1,105 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::triangle_md3;
+use md3_rs::md3::{LoadMode, Md3Error, Md3Model};
+use std::io::Cursor;
+
+#[test]
+fn strict_mode_reads_ident_and_rejects_junk_prefix()
+{
+    let bytes = triangle_md3();
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+    assert_eq!( m.header.ident, i32::from_le_bytes( *b"IDP3" ) );
+    assert_eq!( m.header.version, 15 );
+
+    let mut prefixed = b"IDP junk".to_vec();
+    prefixed.extend_from_slice( &bytes );
+    match Md3Model::from_bytes( &prefixed ) {
+        Err( Md3Error::BadMagic { offset: 0, found } ) => assert_eq!( &found, b"IDP " ),
+        _ => panic!( "expected BadMagic" )
+    }
+}
+
+#[test]
+fn embedded_mode_finds_header_after_junk_prefix()
+{
+    let mut prefixed = b"IDP3junkIDP".to_vec();
+    prefixed.extend_from_slice( &triangle_md3() );
+    let m = Md3Model::from_reader_with_mode( &mut Cursor::new( &prefixed ), LoadMode::Embedded ).unwrap();
+    assert_eq!( m.surfaces.len(), 1 );
+    assert_eq!( m.surfaces[0].data.xyz_normals[1].xyz, [64, 0, 0] );
+}
+
+#[test]
+fn rejects_unsupported_version()
+{
+    let mut bytes = triangle_md3();
+    bytes[4] = 16;
+    match Md3Model::from_bytes( &bytes ) {
+        Err( Md3Error::UnsupportedVersion { version: 16, .. } ) => {},
+        _ => panic!( "expected UnsupportedVersion" )
+    }
+}
+
+#[test]
+fn truncated_header_is_a_truncated_lump()
+{
+    let bytes = triangle_md3();
+    match Md3Model::from_bytes( &bytes[.. 50] ) {
+        Err( Md3Error::TruncatedLump { structure: "Md3Header", .. } ) => {},
+        r => panic!( "expected TruncatedLump, got {:?}", r.err() )
+    }
+    // A lump cut short by the end of the file is caught before it is read
+    match Md3Model::from_bytes( &bytes[.. bytes.len() - 10] ) {
+        Err( Md3Error::CountOverflow { structure: "Md3XyzNormal", .. } ) => {},
+        r => panic!( "expected CountOverflow, got {:?}", r.err() )
+    }
+}
+
+#[test]
+fn offsets_outside_the_file_are_reported_with_their_structure()
+{
+    let mut bytes = triangle_md3();
+    // ofs_frames
+    bytes[92 .. 96].copy_from_slice( &100_000i32.to_le_bytes() );
+    match Md3Model::from_bytes( &bytes ) {
+        Err( ref e @ Md3Error::OffsetOutOfRange { structure: "Md3Frame", value: 100_000, .. } ) =>
+            assert!( e.to_string().contains( "Md3Frame" ) ),
+        r => panic!( "expected OffsetOutOfRange, got {:?}", r.err() )
+    }
+
+    let mut bytes = triangle_md3();
+    // ofs_surfaces
+    bytes[100 .. 104].copy_from_slice( &( -4i32 ).to_le_bytes() );
+    match Md3Model::from_bytes( &bytes ) {
+        Err( Md3Error::OffsetOutOfRange { structure: "Md3Surface", value: -4, .. } ) => {},
+        r => panic!( "expected OffsetOutOfRange, got {:?}", r.err() )
+    }
+}
+
+#[test]
+fn from_reader_from_bytes_and_load_agree()
+{
+    let bytes = triangle_md3();
+    let from_bytes = Md3Model::from_bytes( &bytes ).unwrap();
+    let from_reader = Md3Model::from_reader( &mut Cursor::new( bytes.clone() ) ).unwrap();
+
+    let path = std::env::temp_dir().join( format!( "md3_rs_header_{}.md3", std::process::id() ) );
+    std::fs::write( &path, &bytes ).unwrap();
+    let loaded = Md3Model::load( path.to_string_lossy().into_owned() ).unwrap();
+    std::fs::remove_file( &path ).unwrap();
+
+    for m in [&from_reader, &loaded].iter() {
+        assert_eq!( m.header.name[..], from_bytes.header.name[..] );
+        assert_eq!( m.frames.len(), from_bytes.frames.len() );
+        assert_eq!( m.surfaces[0].data.xyz_normals[2].xyz, from_bytes.surfaces[0].data.xyz_normals[2].xyz );
+        assert_eq!( m.surfaces[0].data.triangles[0].indices, from_bytes.surfaces[0].data.triangles[0].indices );
+    }
+    match Md3Model::load( "/nonexistent/model.md3".to_string() ) {
+        Err( Md3Error::Io { .. } ) => {},
+        r => panic!( "expected Io, got {:?}", r.err() )
+    }
+}
@@ -0,0 +1,233 @@
+extern crate byteorder;
+extern crate md3_rs;
+#[macro_use]
+extern crate serde_json;
+
+mod common;
+
+use common::{animated_triangle, build_md3, triangle};
+use md3_rs::gltf::Gltf;
+use md3_rs::import::{build_model, ImportError, MeshSurface};
+use md3_rs::math::Vec3;
+use md3_rs::md3::{Md3Model, MD3_MAX_VERTS};
+use std::io::Cursor;
+
+const TWO_MATERIALS : &str = "
+mtllib props.mtl
+v 0 0 0
+v 1 0 0
+v 1 1 0
+v 0 1 0
+vt 0 0
+vt 1 0
+vt 1 1
+vt 0 1
+usemtl textures/props/crate
+f 1/1 2/2 3/3 4/4
+usemtl textures/props/lid
+f -4/1 -2/3 -1/4
+";
+
+#[test]
+fn obj_splits_surfaces_by_material_and_quantizes()
+{
+    let m = Md3Model::import_obj( Cursor::new( TWO_MATERIALS ), "props" ).unwrap();
+    assert_eq!( m.surfaces.len(), 2 );
+    assert_eq!( m.frames.len(), 1 );
+
+    let quad = &m.surfaces[0];
+    assert_eq!( quad.data.shaders[0].name_str(), "textures/props/crate" );
+    assert_eq!( quad.header.vertex_count, 4 );
+    assert_eq!( quad.data.triangles.len(), 2 );
+    assert_eq!( quad.data.xyz_normals[2].xyz, [64, 64, 0] );
+    // V is flipped to MD3's top-left origin
+    assert_eq!( quad.data.st_data[2].st, [1.0, 0.0] );
+    // Missing normals are rebuilt from the faces: +Z
+    let n = quad.data.xyz_normals[0].decode_normal();
+    assert!( n[2] > 0.99 );
+
+    let lid = &m.surfaces[1];
+    assert_eq!( lid.data.shaders[0].name_str(), "textures/props/lid" );
+    // Rewound to MD3's clockwise fronts
+    assert_eq!( lid.data.triangles[0].indices, [0, 2, 1] );
+
+    // The result saves and loads back
+    let mut out = Cursor::new( vec![] );
+    m.write_to( &mut out ).unwrap();
+    let back = Md3Model::from_bytes( out.get_ref() ).unwrap();
+    assert_eq!( back.surfaces.len(), 2 );
+}
+
+#[test]
+fn obj_normals_are_normalized_before_encoding()
+{
+    let obj = "v 0 0 0\nv 1 0 0\nv 0 1 0\nvn 0 3 4\nf 1//1 2//1 3//1\n";
+    let m = Md3Model::import_obj( Cursor::new( obj ), "tri" ).unwrap();
+    let n = m.surfaces[0].data.xyz_normals[0].decode_normal();
+    assert!( ( n[1] - 0.6 ).abs() < 0.05, "{:?}", n );
+    assert!( ( n[2] - 0.8 ).abs() < 0.05, "{:?}", n );
+}
+
+#[test]
+fn obj_surfaces_over_the_vertex_limit_are_split()
+{
+    let mut obj = String::new();
+    let tris = MD3_MAX_VERTS / 3 + 10;
+    for i in 0 .. tris {
+        let x = ( i % 64 ) as f32;
+        let y = ( i / 64 ) as f32;
+        obj.push_str( &format!( "v {} {} 0\nv {} {} 0\nv {} {} 0\n", x, y, x + 0.5, y, x, y + 0.5 ) );
+        obj.push_str( &format!( "f {} {} {}\n", i * 3 + 1, i * 3 + 2, i * 3 + 3 ) );
+    }
+    let m = Md3Model::import_obj( Cursor::new( obj ), "big" ).unwrap();
+    assert_eq!( m.surfaces.len(), 2 );
+    assert!( m.surfaces.iter().all(| s | s.header.vertex_count as usize <= MD3_MAX_VERTS ) );
+    let total : i32 = m.surfaces.iter().map(| s | s.header.triangle_count ).sum();
+    assert_eq!( total as usize, tris );
+}
+
+#[test]
+fn gltf_round_trip_keeps_frames_and_materials()
+{
+    let bytes = build_md3( 2, &[ animated_triangle( "body", "models/body.tga" ) ] );
+    let src = Md3Model::from_bytes( &bytes ).unwrap();
+
+    let mut glb = vec![];
+    Gltf::from_model( &src, None, 10.0 ).unwrap().write_glb( &mut glb ).unwrap();
+    let m = Gltf::from_glb( &glb ).unwrap().to_model( "body" ).unwrap();
+
+    assert_eq!( m.frames.len(), 2 );
+    let surf = &m.surfaces[0];
+    assert_eq!( surf.data.shaders[0].name_str(), "models/body.tga" );
+    assert_eq!( surf.frame_vertices( 0 )[1].xyz, [64, 0, 0] );
+    assert_eq!( surf.frame_vertices( 1 )[1].xyz, [128, 0, 64] );
+    assert_eq!( surf.frame_vertices( 1 )[2].xyz, [0, 128, 64] );
+    assert_eq!( surf.data.st_data[2].st, [0.0, 1.0] );
+}
+
+/// Points TEXCOORD_0 of the first primitive at a new normalized accessor
+/// holding `bytes` as pairs of `component_type`.
+fn with_normalized_uvs( g : &mut Gltf, component_type : u32, bytes : &[u8] )
+{
+    let offset = g.bin.len();
+    g.bin.extend_from_slice( bytes );
+    g.json["buffers"][0]["byteLength"] = json!( g.bin.len() );
+    let view = g.json["bufferViews"].as_array().unwrap().len();
+    g.json["bufferViews"].as_array_mut().unwrap().push( json!( {
+        "buffer": 0, "byteOffset": offset, "byteLength": bytes.len()
+    } ) );
+    let acc = g.json["accessors"].as_array().unwrap().len();
+    g.json["accessors"].as_array_mut().unwrap().push( json!( {
+        "bufferView": view, "componentType": component_type, "normalized": true, "count": 3, "type": "VEC2"
+    } ) );
+    g.json["meshes"][0]["primitives"][0]["attributes"]["TEXCOORD_0"] = json!( acc );
+}
+
+#[test]
+fn gltf_normalized_uvs_are_scaled()
+{
+    let bytes = build_md3( 1, &[ triangle( "body", "models/body.tga" ) ] );
+    let src = Md3Model::from_bytes( &bytes ).unwrap();
+
+    // UNSIGNED_SHORT: 0, 65535 and 32768 over 65535
+    let mut g = Gltf::from_model( &src, None, 10.0 ).unwrap();
+    let mut uvs = vec![];
+    for v in [0u16, 65535, 65535, 0, 32768, 65535].iter() {
+        uvs.extend_from_slice( &v.to_le_bytes() );
+    }
+    with_normalized_uvs( &mut g, 5123, &uvs );
+    let m = g.to_model( "body" ).unwrap();
+    let st = &m.surfaces[0].data.st_data;
+    assert_eq!( st[0].st, [0.0, 1.0] );
+    assert_eq!( st[1].st, [1.0, 0.0] );
+    assert!( ( st[2].st[0] - 0.5 ).abs() < 1e-4 );
+
+    // BYTE: -128 clamps to -1 like -127 does
+    let mut g = Gltf::from_model( &src, None, 10.0 ).unwrap();
+    with_normalized_uvs( &mut g, 5120, &[0x80, 0x81, 127, 0, 0, 127] );
+    let m = g.to_model( "body" ).unwrap();
+    let st = &m.surfaces[0].data.st_data;
+    assert_eq!( st[0].st, [-1.0, -1.0] );
+    assert_eq!( st[1].st, [1.0, 0.0] );
+    assert_eq!( st[2].st, [0.0, 1.0] );
+}
+
+/// Whether every triangle of `m`, read with MD3's clockwise fronts, faces
+/// the way its vertex normals point.
+fn fronts_follow_normals( m : &Md3Model ) -> bool
+{
+    m.surfaces.iter().all(| surf | {
+        let verts = surf.frame_vertices( 0 );
+        surf.data.triangles.iter().all(| t | {
+            let p : Vec<Vec3> = t.indices.iter().map(| &i | Vec3::from_array( verts[i as usize].decode_xyz() ) ).collect();
+            let face = ( p[2] - p[0] ).cross( p[1] - p[0] );
+            t.indices.iter().all(| &i | face.dot( Vec3::from_array( verts[i as usize].decode_normal() ) ) > 0.0 )
+        })
+    })
+}
+
+#[test]
+fn winding_survives_obj_and_gltf_round_trips()
+{
+    // Clockwise seen from +Z, the side the (zero-encoded) normals point to
+    let mut tri = triangle( "body", "models/body.tga" );
+    tri.triangles = vec![[0, 2, 1]];
+    let bytes = build_md3( 1, &[ tri ] );
+    let src = Md3Model::from_bytes( &bytes ).unwrap();
+    assert!( fronts_follow_normals( &src ) );
+
+    let mut obj = vec![];
+    src.export_obj( 0, &mut obj ).unwrap();
+    let m = Md3Model::import_obj( Cursor::new( obj ), "body" ).unwrap();
+    assert!( fronts_follow_normals( &m ) );
+
+    let mut glb = vec![];
+    Gltf::from_model( &src, None, 10.0 ).unwrap().write_glb( &mut glb ).unwrap();
+    let m = Gltf::from_glb( &glb ).unwrap().to_model( "body" ).unwrap();
+    assert!( fronts_follow_normals( &m ) );
+    assert_eq!( m.surfaces[0].data.triangles[0].indices, [0, 2, 1] );
+}
+
+#[test]
+fn gltf_attribute_counts_must_match_positions()
+{
+    let bytes = build_md3( 2, &[ animated_triangle( "body", "models/body.tga" ) ] );
+    let src = Md3Model::from_bytes( &bytes ).unwrap();
+    let prim = &Gltf::from_model( &src, None, 10.0 ).unwrap().json["meshes"][0]["primitives"][0];
+    let normal = prim["attributes"]["NORMAL"].as_u64().unwrap() as usize;
+    let morph = prim["targets"][0]["POSITION"].as_u64().unwrap() as usize;
+    for &acc in [ normal, morph ].iter() {
+        let mut g = Gltf::from_model( &src, None, 10.0 ).unwrap();
+        g.json["accessors"][acc]["count"] = json!( 2 );
+        match g.to_model( "body" ) {
+            Err( ImportError::Invalid { .. } ) => {},
+            other => panic!( "{:?} for a short accessor {}", other.map(| _ | () ), acc ),
+        }
+    }
+}
+
+#[test]
+fn build_model_rejects_surfaces_that_disagree_on_vertex_count()
+{
+    let surface = || MeshSurface {
+        name: "tri".to_string(), material: String::new(),
+        positions: vec![ vec![ [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0] ] ],
+        normals: vec![ vec![ [0.0, 0.0, 1.0]; 3 ] ],
+        uvs: vec![ [0.0, 0.0]; 3 ],
+        triangles: vec![ [0, 2, 1] ],
+    };
+    assert_eq!( build_model( "tri", vec![ surface() ] ).unwrap().surfaces[0].header.vertex_count, 3 );
+
+    let mut short_uvs = surface();
+    short_uvs.uvs.pop();
+    let mut short_normals = surface();
+    short_normals.normals[0].pop();
+    let mut bad_index = surface();
+    bad_index.triangles[0][1] = 3;
+    for surf in [ short_uvs, short_normals, bad_index ] {
+        match build_model( "tri", vec![ surf ] ) {
+            Err( ImportError::Invalid { .. } ) => {},
+            other => panic!( "{:?}", other.map(| _ | () ) ),
+        }
+    }
+}
@@ -0,0 +1,73 @@
+extern crate md3_rs;
+
+use md3_rs::md3::Md3XyzNormal;
+
+fn decode( normal : i16 ) -> [f32; 3]
+{
+    Md3XyzNormal { xyz: [0, 0, 0], normal }.decode_normal()
+}
+
+fn dot( a : [f32; 3], b : [f32; 3] ) -> f32
+{
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+/// Cosine of the angle between two decoded normals, which the engine's
+/// sine table leaves slightly shorter than unit length.
+fn cos_angle( a : [f32; 3], b : [f32; 3] ) -> f32
+{
+    dot( a, b ) / ( dot( a, a ) * dot( b, b ) ).sqrt()
+}
+
+#[test]
+fn decoded_normals_match_the_engine_bit_for_bit()
+{
+    // reference bits from tr.sinTable lookups as done by the renderer
+    let cases : [(u16, [u32; 3]); 6] = [
+        ( 0x0000, [0x0000_0000, 0x0000_0000, 0x3f7f_ffec] ),
+        ( 0x0080, [0xbb49_40dc, 0x8000_0000, 0xbf7f_ff4e] ),
+        ( 0x0040, [0x3f7f_ffd8, 0x0000_0000, 0xbb49_40ec] ),
+        ( 0x4040, [0xbb49_40dc, 0x3f7f_ffd8, 0xbb49_40ec] ),
+        ( 0x1234, [0x3f5d_555b, 0x3ed1_c0a7, 0x3e93_42fb] ),
+        ( 0xdf35, [0x3f2a_5218, 0xbf31_d738, 0x3e87_2bd9] ),
+    ];
+    for &( code, bits ) in cases.iter() {
+        let v = decode( code as i16 );
+        let found = [v[0].to_bits(), v[1].to_bits(), v[2].to_bits()];
+        assert_eq!( found, bits, "code {:#06x}: {:?}", code, v );
+    }
+}
+
+#[test]
+fn normal_round_trip_over_every_code()
+{
+    // q3data encodes in 255ths of a turn, truncating negative latitudes
+    // towards zero, while the engine decodes in 256ths, so a decoded normal
+    // re-encodes to within three steps of where it started
+    let tolerance = ( 3.0 * 2.0 * ::std::f32::consts::PI / 256.0 ).cos();
+    for code in i16::MIN ..= i16::MAX {
+        let v = decode( code );
+        let back = decode( Md3XyzNormal::encode_normal( v ) );
+        assert!( cos_angle( v, back ) >= tolerance, "code {:#06x}: {:?} -> {:?}", code, v, back );
+    }
+}
+
+#[test]
+fn poles_and_axes_encode_to_fixed_codes()
+{
+    assert_eq!( Md3XyzNormal::encode_normal( [0.0, 0.0, 1.0] ), 0 );
+    assert_eq!( Md3XyzNormal::encode_normal( [0.0, 0.0, -1.0] ), 128 );
+    let x = decode( Md3XyzNormal::encode_normal( [1.0, 0.0, 0.0] ) );
+    assert!( x[0] > 0.999 );
+}
+
+#[test]
+fn xyz_encoding_truncates_and_clamps()
+{
+    assert_eq!( Md3XyzNormal::encode_xyz( [1.0, -2.5, 0.03] ), [64, -160, 1] );
+    assert_eq!( Md3XyzNormal::encode_xyz( [0.01, -0.01, 0.0] ), [0, 0, 0] );
+    assert_eq!( Md3XyzNormal::encode_xyz( [1.0e6, -1.0e6, 0.0] ), [i16::MAX, i16::MIN, 0] );
+
+    let v = Md3XyzNormal { xyz: [-300, 12, 32767], normal: 0 };
+    assert_eq!( Md3XyzNormal::encode_xyz( v.decode_xyz() ), v.xyz );
+}
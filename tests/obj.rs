@@ -0,0 +1,78 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::build_md3;
+use md3_rs::md3::Md3Model;
+use std::io;
+
+fn two_surfaces() -> Md3Model
+{
+    Md3Model::from_bytes( &build_md3( 2, &common::two_surfaces( 2 ) ) ).unwrap()
+}
+
+#[test]
+fn writes_frame_geometry_with_groups_and_global_indices()
+{
+    let mut out = vec![];
+    two_surfaces().export_obj_with( 1, &mut out, Some( "model.mtl" ), None ).unwrap();
+    let text = String::from_utf8( out ).unwrap();
+    let lines : Vec<&str> = text.lines().collect();
+
+    assert_eq!( lines[1], "mtllib model.mtl" );
+    assert_eq!( lines[2], "g a" );
+    assert_eq!( lines[3], "usemtl textures/a.tga" );
+    assert_eq!( lines[4], "v 0 0 1" );
+    assert!( lines.contains( &"vt 0 0.75" ) );
+    assert!( lines.contains( &"f 1/1/1 3/3/3 2/2/2" ) );
+    assert!( lines.contains( &"g b" ) );
+    assert!( lines.contains( &"usemtl textures/b.tga" ) );
+    assert!( lines.contains( &"v 2.5 0 0.5" ) );
+    assert!( lines.contains( &"f 4/4/4 6/6/6 5/5/5" ) );
+    assert!( lines.contains( &"f 6/6/6 7/7/7 5/5/5" ) );
+}
+
+#[test]
+fn mtl_lists_each_shader_once()
+{
+    let mut specs = common::two_surfaces( 2 );
+    specs[1].shader = "textures/a.tga";
+    let mut out = vec![];
+    Md3Model::from_bytes( &build_md3( 2, &specs ) ).unwrap().export_mtl( &mut out, None ).unwrap();
+    let text = String::from_utf8( out ).unwrap();
+    assert_eq!( text, "newmtl textures/a.tga\nKd 1 1 1\nmap_Kd textures/a.tga\n\n" );
+}
+
+#[test]
+fn frame_out_of_range_is_invalid_input()
+{
+    let mut out = vec![];
+    let err = two_surfaces().export_obj( 2, &mut out ).unwrap_err();
+    assert_eq!( err.kind(), io::ErrorKind::InvalidInput );
+    assert!( out.is_empty() );
+}
+
+#[test]
+fn st_count_mismatch_is_invalid_input()
+{
+    let mut m = two_surfaces();
+    m.surfaces[1].data.st_data.pop();
+    let mut out = vec![];
+    let err = m.export_obj( 0, &mut out ).unwrap_err();
+    assert_eq!( err.kind(), io::ErrorKind::InvalidInput );
+    assert!( out.is_empty() );
+}
+
+#[test]
+fn out_of_range_index_is_invalid_input()
+{
+    for &bad in [ -1, 4 ].iter() {
+        let mut m = two_surfaces();
+        m.surfaces[1].data.triangles[0].indices[2] = bad;
+        let mut out = vec![];
+        let err = m.export_obj( 0, &mut out ).unwrap_err();
+        assert_eq!( err.kind(), io::ErrorKind::InvalidInput );
+        assert!( out.is_empty() );
+    }
+}
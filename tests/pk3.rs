@@ -0,0 +1,57 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use std::io::Cursor;
+use common::{build_pk3, triangle_md3};
+use md3_rs::pk3::{Pk3, Pk3Error};
+
+fn archive() -> Pk3<Cursor<Vec<u8>>>
+{
+    let md3 = triangle_md3();
+    let bytes = build_pk3( &[
+        ( "models/", b"" ),
+        ( "models/mapobjects/", b"" ),
+        ( "Models/MapObjects/Tri.MD3", &md3 ),
+        ( "models/mapobjects/tri.skin", b"tri,models/mapobjects/tri.tga\n" ),
+        ( "sound/tri.md3", &md3 ),
+        ( "maps/q3dm1.bsp", b"IBSP" ),
+    ] );
+    Pk3::from_reader( Cursor::new( bytes ) ).unwrap()
+}
+
+#[test]
+fn lists_file_entries_and_models()
+{
+    let pk3 = archive();
+    assert_eq!( pk3.entries().len(), 4 );
+    assert_eq!( pk3.models(), vec!["Models/MapObjects/Tri.MD3"] );
+    assert!( pk3.contains( "models\\mapobjects\\TRI.skin" ) );
+    assert!( !pk3.contains( "models/mapobjects" ) );
+}
+
+#[test]
+fn loads_md3_and_text_entries_case_insensitively()
+{
+    let mut pk3 = archive();
+    let model = pk3.load_md3( "models/mapobjects/tri.md3" ).unwrap();
+    assert_eq!( model.surfaces[0].header.name_str(), "tri" );
+
+    let skin = pk3.read_to_string( "models/mapobjects/tri.skin" ).unwrap();
+    assert!( skin.starts_with( "tri," ) );
+}
+
+#[test]
+fn reports_missing_and_unloadable_entries()
+{
+    let mut pk3 = archive();
+    match pk3.load_md3( "models/nothere.md3" ) {
+        Err( Pk3Error::NotFound( name ) ) => assert_eq!( name, "models/nothere.md3" ),
+        _ => panic!( "expected NotFound" ),
+    }
+    match pk3.load_md3( "maps/q3dm1.bsp" ) {
+        Err( Pk3Error::Md3 { name, .. } ) => assert_eq!( name, "maps/q3dm1.bsp" ),
+        _ => panic!( "expected an md3 error" ),
+    }
+}
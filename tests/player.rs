@@ -0,0 +1,45 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::{tag_model, TagSpec};
+use md3_rs::math::{Transform, Vec3};
+use md3_rs::md3::Md3Model;
+use md3_rs::player::{FrameLerp, PlayerModel};
+
+const IDENTITY : [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+// 90 degrees about z: forward becomes left
+const YAW_90 : [[f32; 3]; 3] = [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+
+// One tag per frame
+fn model( tags : &[TagSpec] ) -> Md3Model
+{
+    tag_model( tags.len().max( 1 ) as i32, tags )
+}
+
+#[test]
+fn composes_torso_and_head_through_tags()
+{
+    let player = PlayerModel {
+        lower: model( &[
+            TagSpec { name: "tag_torso", origin: [0.0, 0.0, 10.0], axis: YAW_90 },
+            TagSpec { name: "tag_torso", origin: [0.0, 0.0, 20.0], axis: YAW_90 },
+        ] ),
+        upper: model( &[ TagSpec { name: "tag_head", origin: [5.0, 0.0, 0.0], axis: IDENTITY } ] ),
+        head: model( &[] ),
+    };
+
+    let legs = FrameLerp { frame_a: 0, frame_b: 1, t: 0.5 };
+    let tr = player.transforms( &Transform::identity(), legs, FrameLerp::frame( 0 ) ).unwrap();
+    assert_eq!( tr.lower, Transform::identity() );
+    assert_eq!( tr.upper.origin, Vec3::new( 0.0, 0.0, 15.0 ) );
+    assert_eq!( tr.head.origin, Vec3::new( 0.0, 5.0, 15.0 ) );
+    assert_eq!( tr.head.axis[0], Vec3::new( 0.0, 1.0, 0.0 ) );
+
+    let moved = Transform { origin: Vec3::new( 100.0, 0.0, 0.0 ), ..Transform::identity() };
+    let tr = player.transforms( &moved, FrameLerp::frame( 0 ), FrameLerp::frame( 0 ) ).unwrap();
+    assert_eq!( tr.head.origin, Vec3::new( 100.0, 5.0, 10.0 ) );
+
+    assert!( player.transforms( &moved, FrameLerp::frame( 0 ), FrameLerp::frame( 3 ) ).is_none() );
+}
@@ -0,0 +1,66 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::{tag_model, TagSpec};
+use md3_rs::math::{Transform, Vec3};
+use md3_rs::md3::Md3Model;
+use md3_rs::animation::FrameLerp;
+use md3_rs::scene::{SceneError, SceneGraph};
+use std::rc::Rc;
+
+const IDENTITY : [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+
+fn model( frames : i32, tags : &[TagSpec] ) -> Rc<Md3Model>
+{
+    Rc::new( tag_model( frames, tags ) )
+}
+
+#[test]
+fn evaluates_weapon_and_flash_chain()
+{
+    let torso = model( 2, &[
+        TagSpec { name: "tag_weapon", origin: [10.0, 0.0, 0.0], axis: IDENTITY },
+        TagSpec { name: "tag_weapon", origin: [20.0, 0.0, 0.0], axis: IDENTITY },
+    ] );
+    let weapon = model( 1, &[ TagSpec { name: "tag_flash", origin: [0.0, 0.0, 4.0], axis: IDENTITY } ] );
+    let flash = model( 1, &[] );
+
+    let mut scene = SceneGraph::new();
+    let root = Transform { origin: Vec3::new( 0.0, 100.0, 0.0 ), ..Transform::identity() };
+    let t = scene.add_root( torso, root );
+    let w = scene.attach( weapon, t, "tag_weapon" );
+    let f = scene.attach( flash, w, "tag_flash" );
+
+    let world = scene.world_transforms().unwrap();
+    assert_eq!( world[f].origin, Vec3::new( 10.0, 100.0, 4.0 ) );
+
+    scene.set_frame( t, FrameLerp { frame_a: 0, frame_b: 1, t: 0.5 } );
+    let m = scene.world_matrices().unwrap();
+    assert_eq!( m[w][3], [15.0, 100.0, 0.0, 1.0] );
+    assert_eq!( m[f][3], [15.0, 100.0, 4.0, 1.0] );
+}
+
+#[test]
+fn missing_tag_names_the_node()
+{
+    let mut scene = SceneGraph::new();
+    let t = scene.add_root( model( 1, &[] ), Transform::identity() );
+    let w = scene.attach( model( 1, &[] ), t, "tag_weapon" );
+    assert_eq!( scene.world_transforms().unwrap_err(),
+                SceneError::MissingTag { node: w, tag: "tag_weapon".to_string() } );
+}
+
+#[test]
+fn parents_edited_out_of_order_are_an_error()
+{
+    let weapon = || model( 1, &[ TagSpec { name: "tag_weapon", origin: [0.0; 3], axis: IDENTITY } ] );
+    let mut scene = SceneGraph::new();
+    let t = scene.add_root( weapon(), Transform::identity() );
+    let w = scene.attach( weapon(), t, "tag_weapon" );
+    for &bad in [ w, w + 1 ].iter() {
+        scene.nodes[w].parent = Some( ( bad, "tag_weapon".to_string() ) );
+        assert_eq!( scene.world_transforms().unwrap_err(), SceneError::BadParent { node: w, parent: bad } );
+    }
+}
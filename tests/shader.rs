@@ -0,0 +1,149 @@
+extern crate md3_rs;
+
+use md3_rs::shader::*;
+
+const SCRIPT : &str = r#"
+// sarge's skin
+models/players/sarge/band
+{
+    qer_editorimage models/players/sarge/band.tga
+    surfaceparm nodraw
+    cull disable
+    deformVertexes wave 100 sin 0 1.5 0 0.4
+    sort additive
+    {
+        map models/players/sarge/band.tga
+        blendFunc GL_ONE GL_ZERO
+        rgbGen lightingDiffuse
+        alphaFunc GE128
+    }
+    {
+        map $lightmap
+        blendfunc filter
+        tcGen environment
+        tcMod scroll 0.1 -0.2
+        tcMod turb 0 0.25 0 0.5
+        rgbGen const ( 1 0.5 0 )
+        depthFunc equal
+    }
+}
+
+textures/sfx/flame
+{
+    cull none
+    {
+        animMap 10 textures/sfx/f1.tga textures/sfx/f2.tga textures/sfx/f3.tga
+        blendFunc add
+        alphaGen wave square 0 1 0.25 2
+        tcMod transform 1 0 0 1 0.5 0.5
+    }
+}
+
+Models/Players/Sarge/Band
+{
+    cull back
+}
+"#;
+
+#[test]
+fn parses_general_keywords_and_stages()
+{
+    let set = ShaderSet::parse( SCRIPT ).unwrap();
+    assert_eq!( set.shaders.len(), 2 );
+
+    // first definition wins; lookup ignores case and extension
+    let band = set.get( "MODELS/players/sarge/band.tga" ).unwrap();
+    assert_eq!( band.name, "models/players/sarge/band" );
+    assert_eq!( band.cull, Cull::TwoSided );
+    assert!( band.has_surface_param( "NoDraw" ) );
+    assert_eq!( band.sort, Some( 10.0 ) );
+    assert_eq!( band.deforms, vec![DeformVertexes::Wave {
+        div: 100.0,
+        wave: Wave { func: WaveFunc::Sin, base: 0.0, amplitude: 1.5, phase: 0.0, frequency: 0.4 }
+    }] );
+
+    assert_eq!( band.stages.len(), 2 );
+    let base = &band.stages[0];
+    assert_eq!( base.map, Some( StageMap::Map( "models/players/sarge/band.tga".to_string() ) ) );
+    assert_eq!( base.blend_func, Some( ( BlendFactor::One, BlendFactor::Zero ) ) );
+    assert_eq!( base.rgb_gen, Some( RgbGen::LightingDiffuse ) );
+    assert_eq!( base.alpha_func, Some( AlphaFunc::Ge128 ) );
+
+    let light = &band.stages[1];
+    assert_eq!( light.map, Some( StageMap::Lightmap ) );
+    assert_eq!( light.blend_func, Some( ( BlendFactor::DstColor, BlendFactor::Zero ) ) );
+    assert_eq!( light.tc_gen, Some( TcGen::Environment ) );
+    assert_eq!( light.tc_mods, vec![
+        TcMod::Scroll( [0.1, -0.2] ),
+        TcMod::Turb { base: 0.0, amplitude: 0.25, phase: 0.0, frequency: 0.5 },
+    ] );
+    assert_eq!( light.rgb_gen, Some( RgbGen::Const( [1.0, 0.5, 0.0] ) ) );
+    assert_eq!( light.depth_func, Some( DepthFunc::Equal ) );
+
+    assert_eq!( band.images(), vec!["models/players/sarge/band.tga"] );
+}
+
+#[test]
+fn parses_anim_maps_and_shorthand_blends()
+{
+    let set = ShaderSet::parse( SCRIPT ).unwrap();
+    let flame = set.get( "textures/sfx/flame" ).unwrap();
+    let stage = &flame.stages[0];
+    match stage.map {
+        Some( StageMap::AnimMap { frequency, ref images } ) => {
+            assert_eq!( frequency, 10.0 );
+            assert_eq!( images.len(), 3 );
+        },
+        ref m => panic!( "expected an animMap, got {:?}", m ),
+    }
+    assert_eq!( stage.blend_func, Some( ( BlendFactor::One, BlendFactor::One ) ) );
+    assert_eq!( stage.alpha_gen, Some( AlphaGen::Wave(
+        Wave { func: WaveFunc::Square, base: 0.0, amplitude: 1.0, phase: 0.25, frequency: 2.0 } ) ) );
+    assert_eq!( stage.tc_mods, vec![TcMod::Transform { matrix: [[1.0, 0.0], [0.0, 1.0]], translate: [0.5, 0.5] }] );
+    assert_eq!( flame.images().len(), 3 );
+}
+
+#[test]
+fn reports_bad_values_and_unterminated_shaders()
+{
+    match ShaderSet::parse( "a\n{\n{\nblendFunc GL_ONE GL_BOGUS\n}\n}\n" ) {
+        Err( ShaderError::BadValue { line, token } ) => {
+            assert_eq!( line, 4 );
+            assert_eq!( token, "gl_bogus" );
+        },
+        _ => panic!( "expected BadValue" ),
+    }
+    match ShaderSet::parse( "a\n{\ncull none\n" ) {
+        Err( ShaderError::UnexpectedEnd { shader } ) => assert_eq!( shader, "a" ),
+        _ => panic!( "expected UnexpectedEnd" ),
+    }
+    match ShaderSet::parse( "a\ncull none\n" ) {
+        Err( ShaderError::Expected { expected, .. } ) => assert_eq!( expected, "{" ),
+        _ => panic!( "expected a missing brace" ),
+    }
+}
+
+#[test]
+fn add_script_skips_bad_stages_and_shaders()
+{
+    let mut set = ShaderSet::new();
+    set.add_script( "bad.shader", "a\n{\n{\nmap a.tga\n}\n{\nblendFunc GL_ONE GL_BOGUS\nmap x.tga\n}\n}\n\
+                                   b\n{\nsort nowhere\n{\nmap b.tga\n}\n}\n\
+                                   c\n{\n{\nmap c.tga\n}\n}\n" );
+    assert_eq!( set.get( "a" ).unwrap().images(), vec!["a.tga"] );
+    assert!( set.get( "b" ).is_none() );
+    assert_eq!( set.get( "c" ).unwrap().images(), vec!["c.tga"] );
+    assert_eq!( set.warnings.len(), 2 );
+    match set.warnings[0].error {
+        ShaderError::BadValue { line, .. } => assert_eq!( line, 7 ),
+        ref e => panic!( "expected BadValue, got {:?}", e ),
+    }
+    assert_eq!( set.warnings[1].script, "bad.shader" );
+}
+
+#[test]
+fn non_ascii_block_comments_are_skipped()
+{
+    let set = ShaderSet::parse( "/* © id software */\na\n{\n}\n" ).unwrap();
+    assert!( set.get( "a" ).is_some() );
+}
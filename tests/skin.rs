@@ -0,0 +1,28 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::{build_md3, triangle};
+use md3_rs::md3::Md3Model;
+use md3_rs::skin::Skin;
+
+#[test]
+fn skin_overrides_md3_shaders_by_surface_name()
+{
+    let bytes = build_md3( 1, &[
+        triangle( "h_head", "models/players/sarge/default.tga" ),
+        triangle( "h_cigar", "models/players/sarge/cigar.tga" ),
+    ] );
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+
+    let skin = Skin::parse( "tag_head,\r\nH_Head,models/players/sarge/band.tga\r\n" );
+    assert_eq!( skin.surfaces.len(), 1 );
+    assert_eq!( skin.shader_for( "h_head" ), Some( "models/players/sarge/band.tga" ) );
+
+    assert_eq!( m.resolve_shaders( Some( &skin ) ), vec![
+        Some( "models/players/sarge/band.tga" ),
+        Some( "models/players/sarge/cigar.tga" ),
+    ] );
+    assert_eq!( m.resolve_shaders( None )[0], Some( "models/players/sarge/default.tga" ) );
+}
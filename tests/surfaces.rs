@@ -0,0 +1,161 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::{animated_triangle, build_md3, build_md3_with_tags, triangle, SurfaceSpec, TagSpec};
+use md3_rs::md3::{qpath_str, Md3Error, Md3Model};
+
+#[test]
+fn loads_every_surface_of_a_multi_surface_model()
+{
+    let bytes = build_md3( 1, &[
+        SurfaceSpec {
+            name: "h_head", shader: "models/head.tga",
+            xyz: vec![[1, 2, 3], [4, 5, 6], [7, 8, 9]],
+            st: vec![[0.0, 0.0], [1.0, 0.0], [0.0, 1.0]],
+            triangles: vec![[0, 1, 2]],
+        },
+        SurfaceSpec {
+            name: "h_visor", shader: "models/visor.tga",
+            xyz: vec![[10, 11, 12], [13, 14, 15], [16, 17, 18], [19, 20, 21]],
+            st: vec![[0.5, 0.5], [1.0, 1.0], [0.0, 1.0], [1.0, 0.0]],
+            triangles: vec![[0, 1, 2], [2, 3, 0]],
+        },
+    ] );
+
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+    assert_eq!( m.surfaces.len(), 2 );
+
+    let head = &m.surfaces[0];
+    assert_eq!( qpath_str( &head.header.name ), "h_head" );
+    assert_eq!( qpath_str( &head.data.shaders[0].name ), "models/head.tga" );
+    assert_eq!( head.data.xyz_normals[2].xyz, [7, 8, 9] );
+    assert_eq!( head.data.triangles[0].indices, [0, 1, 2] );
+
+    let visor = &m.surfaces[1];
+    assert_eq!( qpath_str( &visor.header.name ), "h_visor" );
+    assert_eq!( qpath_str( &visor.data.shaders[0].name ), "models/visor.tga" );
+    assert_eq!( visor.data.xyz_normals.len(), 4 );
+    assert_eq!( visor.data.xyz_normals[3].xyz, [19, 20, 21] );
+    assert_eq!( visor.data.triangles[1].indices, [2, 3, 0] );
+}
+
+#[test]
+fn loads_vertices_for_every_frame()
+{
+    let bytes = build_md3( 2, &[ animated_triangle( "anim", "anim.tga" ) ] );
+
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+    let surf = &m.surfaces[0];
+    assert_eq!( surf.data.xyz_normals.len(), 6 );
+    assert_eq!( surf.frame_vertices( 0 )[1].xyz, [64, 0, 0] );
+    assert_eq!( surf.frame_vertices( 1 )[1].xyz, [128, 0, 64] );
+    assert!( surf.frame_vertices( 2 ).is_empty() );
+    assert!( surf.frame_vertices( usize::MAX ).is_empty() );
+    assert!( surf.frame_vertices( usize::MAX / 3 + 1 ).is_empty() );
+}
+
+#[test]
+fn reads_one_st_per_vertex_when_triangle_count_differs()
+{
+    let bytes = build_md3( 1, &[
+        SurfaceSpec {
+            name: "quad", shader: "quad.tga",
+            xyz: vec![[0, 0, 0], [64, 0, 0], [64, 64, 0], [0, 64, 0], [32, 32, 32]],
+            st: vec![[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 1.0], [0.5, 0.5]],
+            triangles: vec![[0, 1, 2], [2, 3, 0]],
+        },
+    ] );
+
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+    let surf = &m.surfaces[0];
+    assert_eq!( surf.header.triangle_count, 2 );
+    assert_eq!( surf.header.vertex_count, 5 );
+    assert_eq!( surf.data.st_data.len(), 5 );
+    assert_eq!( surf.data.st_data[4].st, [0.5, 0.5] );
+    assert_eq!( surf.frame_vertices( 0 )[4].xyz, [32, 32, 32] );
+}
+
+#[test]
+fn errors_when_st_lump_is_shorter_than_vertex_count()
+{
+    let bytes = build_md3( 1, &[
+        SurfaceSpec {
+            name: "short", shader: "short.tga",
+            xyz: vec![[0, 0, 0], [64, 0, 0], [0, 64, 0]],
+            st: vec![[0.0, 0.0]],
+            triangles: vec![[0, 1, 2]],
+        },
+    ] );
+
+    match Md3Model::from_bytes( &bytes ) {
+        Err( Md3Error::CountMismatch { structure: "Md3St", expected: 1, found: 3, .. } ) => {},
+        _ => panic!( "expected CountMismatch" )
+    }
+}
+
+#[test]
+fn errors_when_st_lump_runs_past_the_surface_end()
+{
+    let mut bytes = build_md3( 1, &[ triangle( "a", "tri.tga" ), triangle( "b", "tri.tga" ) ] );
+    // Point the first surface's ST lump at its last 8 bytes, after the XYZ
+    // lump, so its three entries would run on into the second surface
+    let surf = i32::from_le_bytes( [bytes[100], bytes[101], bytes[102], bytes[103]] ) as usize;
+    let end = i32::from_le_bytes( [bytes[surf + 104], bytes[surf + 105], bytes[surf + 106], bytes[surf + 107]] );
+    bytes[surf + 96 .. surf + 100].copy_from_slice( &( end - 8 ).to_le_bytes() );
+
+    match Md3Model::from_bytes( &bytes ) {
+        Err( Md3Error::CountMismatch { structure: "Md3St", expected: 1, found: 3, .. } ) => {},
+        r => panic!( "expected CountMismatch, got {:?}", r.err() )
+    }
+}
+
+#[test]
+fn interpolates_positions_between_frames()
+{
+    let bytes = build_md3( 2, &[ animated_triangle( "anim", "anim.tga" ) ] );
+
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+    let surf = &m.surfaces[0];
+    assert_eq!( surf.interpolate( 0, 1, 0.0 )[1], [1.0, 0.0, 0.0] );
+    assert_eq!( surf.interpolate( 0, 1, 0.5 )[1], [1.5, 0.0, 0.5] );
+    assert_eq!( surf.interpolate( 0, 1, 1.0 )[2], [0.0, 2.0, 1.0] );
+    assert!( surf.interpolate( 0, 5, 0.5 ).is_empty() );
+}
+
+#[test]
+fn tags_are_grouped_by_frame_and_looked_up_by_name()
+{
+    let identity = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    let turned = [[0.0, 1.0, 0.0], [-1.0, 0.0, 0.0], [0.0, 0.0, 1.0]];
+    let bytes = build_md3_with_tags( 2, &[
+        TagSpec { name: "tag_head", origin: [0.0, 0.0, 20.0], axis: identity },
+        TagSpec { name: "tag_weapon", origin: [10.0, 0.0, 0.0], axis: identity },
+        TagSpec { name: "tag_head", origin: [0.0, 0.0, 24.0], axis: identity },
+        TagSpec { name: "tag_weapon", origin: [20.0, 4.0, 0.0], axis: turned },
+    ], &[] );
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+
+    assert_eq!( m.tags.len(), 4 );
+    assert_eq!( m.frame_tags( 1 ).len(), 2 );
+    assert_eq!( m.frame_tags( 1 )[0].name_str(), "tag_head" );
+    assert!( m.frame_tags( 2 ).is_empty() );
+    assert!( m.frame_tags( usize::MAX ).is_empty() );
+    assert!( m.frame_tags( usize::MAX / 2 + 1 ).is_empty() );
+
+    let weapon = m.tag( 1, "tag_weapon" ).unwrap();
+    assert_eq!( [weapon.origin.x, weapon.origin.y, weapon.origin.z], [20.0, 4.0, 0.0] );
+    assert_eq!( weapon.axis[1].x, -1.0 );
+    assert_eq!( m.tag( 0, "tag_head" ).unwrap().origin.z, 20.0 );
+    assert!( m.tag( 0, "tag_torso" ).is_none() );
+    assert!( m.tag( 2, "tag_head" ).is_none() );
+
+    let mid = m.lerp_tag( 0, 1, 0.5, "tag_weapon" ).unwrap();
+    assert_eq!( mid.name_str(), "tag_weapon" );
+    assert_eq!( mid.origin.to_array(), [15.0, 2.0, 0.0] );
+    // Halfway between the two forward axes, renormalized
+    let forward = mid.axis[0].to_array();
+    assert!( ( forward[0] - 0.5f32.sqrt() ).abs() < 1e-5 && ( forward[1] - 0.5f32.sqrt() ).abs() < 1e-5 );
+    assert!( m.lerp_tag( 0, 2, 0.5, "tag_weapon" ).is_none() );
+}
@@ -0,0 +1,75 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use std::io::Cursor;
+use common::{build_md3, build_pk3, triangle};
+use md3_rs::md3::Md3Model;
+use md3_rs::pk3::Pk3;
+use md3_rs::shader::ShaderSet;
+use md3_rs::skin::Skin;
+use md3_rs::texture::{find_image, resolve_texture};
+
+fn source() -> Pk3<Cursor<Vec<u8>>>
+{
+    let bytes = build_pk3( &[
+        ( "models/players/sarge/band.jpg", b"" ),
+        ( "models/players/sarge/cigar.tga", b"" ),
+        ( "models/players/sarge/red.png", b"" ),
+        ( "textures/sfx/glow.tga", b"" ),
+    ] );
+    Pk3::from_reader( Cursor::new( bytes ) ).unwrap()
+}
+
+fn shaders() -> ShaderSet
+{
+    ShaderSet::parse( "
+        models/players/sarge/cigar
+        {
+            { map $lightmap }
+            { map textures/sfx/glow.tga }
+            { map textures/sfx/missing.tga }
+        }
+    " ).unwrap()
+}
+
+#[test]
+fn falls_back_through_image_extensions()
+{
+    let src = source();
+    assert_eq!( find_image( &src, "models/players/sarge/band.tga" ),
+                Some( "models/players/sarge/band.jpg".to_string() ) );
+    assert_eq!( find_image( &src, "models/players/sarge/red" ),
+                Some( "models/players/sarge/red.png".to_string() ) );
+    assert_eq!( find_image( &src, "models/players/sarge/cigar.tga" ),
+                Some( "models/players/sarge/cigar.tga".to_string() ) );
+    assert_eq!( find_image( &src, "models/players/sarge/none.tga" ), None );
+}
+
+#[test]
+fn script_shaders_resolve_their_stage_images()
+{
+    let res = resolve_texture( &source(), &shaders(), "models/players/sarge/cigar.tga" );
+    assert_eq!( res.shader.as_deref(), Some( "models/players/sarge/cigar" ) );
+    assert_eq!( res.images, vec!["textures/sfx/glow.tga"] );
+    assert_eq!( res.unresolved, vec!["textures/sfx/missing.tga"] );
+    assert!( !res.is_resolved() );
+}
+
+#[test]
+fn model_surfaces_report_unresolved_references()
+{
+    let model = Md3Model::from_bytes( &build_md3( 1, &[
+        triangle( "u_band", "models/players/sarge/band.tga" ),
+        triangle( "u_torso", "models/players/sarge/torso.tga" ),
+        triangle( "u_empty", "" ),
+    ] ) ).unwrap();
+    let skin = Skin::parse( "u_torso,models/players/sarge/red.tga\n" );
+
+    let res = model.resolve_textures( Some( &skin ), &ShaderSet::new(), &source() );
+    assert_eq!( res[0].images, vec!["models/players/sarge/band.jpg"] );
+    assert_eq!( res[1].images, vec!["models/players/sarge/red.png"] );
+    assert!( res[1].is_resolved() );
+    assert_eq!( res[2].unresolved, vec![""] );
+}
@@ -0,0 +1,95 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::{build_md3, corner};
+use md3_rs::md3::{Md3Model, Md3Triangle, MAX_QPATH, MD3_MAX_VERTS};
+use md3_rs::validate::{Issue, Location, Severity};
+
+fn clean_model() -> Md3Model
+{
+    let bytes = build_md3( 2, &[ corner( "s", "textures/s.tga" ) ] );
+    Md3Model::from_bytes( &bytes ).unwrap()
+}
+
+fn has( model : &Md3Model, severity : Severity, location : Location, issue : Issue ) -> bool
+{
+    model.validate().iter().any(| d | d.severity == severity && d.location == location && d.issue == issue )
+}
+
+#[test]
+fn clean_model_has_no_diagnostics()
+{
+    assert_eq!( clean_model().validate(), vec![] );
+}
+
+#[test]
+fn reports_bad_triangles_and_st()
+{
+    let mut m = clean_model();
+    m.surfaces[0].data.triangles[0].indices = [0, 1, 4];
+    m.surfaces[0].data.triangles.push( Md3Triangle { indices: [1, 1, 2] } );
+    m.surfaces[0].data.triangles.push( Md3Triangle { indices: [0, 1, 1] } );
+    m.surfaces[0].header.triangle_count = 4;
+    m.surfaces[0].data.st_data[2].st[1] = f32::NAN;
+
+    assert!( has( &m, Severity::Error, Location::Triangle { surface: 0, triangle: 0 },
+                  Issue::IndexOutOfRange { index: 4, vertex_count: 4 } ) );
+    assert!( has( &m, Severity::Warning, Location::Triangle { surface: 0, triangle: 2 }, Issue::DegenerateTriangle ) );
+    assert!( has( &m, Severity::Warning, Location::Triangle { surface: 0, triangle: 3 }, Issue::DegenerateTriangle ) );
+    assert!( has( &m, Severity::Error, Location::St { surface: 0, vertex: 2 }, Issue::NonFinite { what: "st" } ) );
+    assert!( !m.validate().iter().any(| d | d.location == Location::Triangle { surface: 0, triangle: 1 } ) );
+}
+
+#[test]
+fn reports_collinear_triangles_as_degenerate()
+{
+    let mut m = clean_model();
+    m.surfaces[0].data.xyz_normals[2].xyz = [32, 0, 0];
+    assert!( has( &m, Severity::Warning, Location::Triangle { surface: 0, triangle: 0 }, Issue::DegenerateTriangle ) );
+}
+
+#[test]
+fn reports_unterminated_names()
+{
+    let mut m = clean_model();
+    m.surfaces[0].header.name = [b'x'; MAX_QPATH];
+    m.surfaces[0].data.shaders[0].name = [b'y'; MAX_QPATH];
+    m.frames[1].name = [b'z'; 16];
+
+    assert!( has( &m, Severity::Error, Location::Surface( 0 ), Issue::UnterminatedName ) );
+    assert!( has( &m, Severity::Error, Location::Shader { surface: 0, shader: 0 }, Issue::UnterminatedName ) );
+    assert!( has( &m, Severity::Error, Location::Frame( 1 ), Issue::UnterminatedName ) );
+}
+
+#[test]
+fn reports_vertices_outside_frame_bounds()
+{
+    let mut m = clean_model();
+    // frame 1, vertex 1 at x = -3 against bounds of +/-1 and radius 2
+    m.surfaces[0].data.xyz_normals[5].xyz = [-192, 0, 0];
+
+    assert!( has( &m, Severity::Warning, Location::SurfaceFrame { surface: 0, frame: 1 },
+                  Issue::OutsideBounds { count: 1, first: 1 } ) );
+    assert!( has( &m, Severity::Warning, Location::SurfaceFrame { surface: 0, frame: 1 },
+                  Issue::OutsideRadius { count: 1, first: 1 } ) );
+    assert!( !m.validate().iter().any(| d | d.location == Location::SurfaceFrame { surface: 0, frame: 0 } ) );
+}
+
+#[test]
+fn reports_engine_limits_and_count_mismatches()
+{
+    let mut m = clean_model();
+    m.surfaces[0].header.vertex_count = MD3_MAX_VERTS as i32 + 1;
+    m.header.tags_count = 17;
+
+    let diags = m.validate();
+    assert!( has( &m, Severity::Error, Location::Surface( 0 ),
+                  Issue::TooMany { what: "vertices", count: MD3_MAX_VERTS + 1, max: MD3_MAX_VERTS } ) );
+    assert!( has( &m, Severity::Error, Location::Surface( 0 ),
+                  Issue::CountMismatch { what: "st coordinates", expected: MD3_MAX_VERTS + 1, found: 4 } ) );
+    assert!( has( &m, Severity::Error, Location::Header, Issue::TooMany { what: "tags", count: 17, max: 16 } ) );
+    assert_eq!( diags.iter().map(| d | d.severity ).max(), Some( Severity::Error ) );
+    assert_eq!( format!( "{}", diags[0] ), "error: header: 17 tags exceeds the MD3 limit of 16" );
+}
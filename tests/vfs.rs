@@ -0,0 +1,130 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process;
+use common::{build_md3, build_pk3, triangle};
+use md3_rs::shader::ShaderSet;
+use md3_rs::vfs::{Vfs, VfsError};
+
+/// A fresh scratch directory for one test.
+fn scratch( test : &str ) -> PathBuf
+{
+    let dir = env::temp_dir().join( format!( "md3_rs_vfs_{}_{}", test, process::id() ) );
+    let _ = fs::remove_dir_all( &dir );
+    fs::create_dir_all( &dir ).unwrap();
+    dir
+}
+
+fn write( path : &Path, bytes : &[u8] )
+{
+    fs::create_dir_all( path.parent().unwrap() ).unwrap();
+    fs::write( path, bytes ).unwrap();
+}
+
+fn named_md3( surface : &'static str ) -> Vec<u8>
+{
+    build_md3( 1, &[ triangle( surface, "" ) ] )
+}
+
+fn path_string( p : &Path ) -> String
+{
+    p.to_string_lossy().into_owned()
+}
+
+#[test]
+fn later_pk3s_and_game_dirs_override_earlier_ones()
+{
+    let root = scratch( "override" );
+    let base = root.join( "baseq3" );
+    let md = root.join( "mymod" );
+
+    write( &base.join( "models/box.md3" ), &named_md3( "loose" ) );
+    write( &base.join( "models/loose_only.skin" ), b"box,loose.tga\n" );
+    // pak0 is older than zz_patch even though it is written second
+    write( &base.join( "zz_patch.pk3" ), &build_pk3( &[( "models/box.md3", &named_md3( "patch" ) )] ) );
+    write( &base.join( "PAK0.pk3" ), &build_pk3( &[
+        ( "models/box.md3", &named_md3( "pak0" ) ),
+        ( "models/box.skin", b"box,pak0.tga\n" ),
+    ] ) );
+    write( &md.join( "models/box.skin" ), b"box,mod.tga\n" );
+
+    let mut vfs = Vfs::new();
+    vfs.add_game_dir( path_string( &base ) ).unwrap();
+
+    let model = vfs.load_md3( "models/box.md3" ).unwrap();
+    assert_eq!( model.surfaces[0].header.name_str(), "patch" );
+    assert_eq!( vfs.source_of( "models/box.md3" ), Some( base.join( "zz_patch.pk3" ).as_path() ) );
+    assert!( vfs.contains( "models/loose_only.skin" ) );
+    assert_eq!( vfs.load_skin( "models/box.skin" ).unwrap().shader_for( "box" ), Some( "pak0.tga" ) );
+
+    vfs.add_game_dir( path_string( &md ) ).unwrap();
+    assert_eq!( vfs.load_skin( "models/box.skin" ).unwrap().shader_for( "box" ), Some( "mod.tga" ) );
+
+    fs::remove_dir_all( &root ).unwrap();
+}
+
+#[test]
+fn lists_files_once_across_layers()
+{
+    let root = scratch( "list" );
+    write( &root.join( "scripts/base.shader" ), b"" );
+    write( &root.join( "scripts/notes.txt" ), b"" );
+    write( &root.join( "pak0.pk3" ), &build_pk3( &[
+        ( "scripts/base.shader", b"" ),
+        ( "scripts/models.shader", b"" ),
+        ( "scripts/old/skip.shader", b"" ),
+    ] ) );
+
+    let mut vfs = Vfs::new();
+    vfs.add_game_dir( path_string( &root ) ).unwrap();
+    assert_eq!( vfs.list_files( "scripts", ".shader" ),
+                vec!["scripts/base.shader", "scripts/models.shader"] );
+
+    fs::remove_dir_all( &root ).unwrap();
+}
+
+#[test]
+fn missing_and_escaping_paths_are_not_found()
+{
+    let root = scratch( "missing" );
+    write( &root.join( "models/a.md3" ), &named_md3( "a" ) );
+
+    let mut vfs = Vfs::new();
+    vfs.add_dir( path_string( &root.join( "models" ) ) );
+    assert!( !vfs.contains( "../models/a.md3" ) );
+    assert!( vfs.list_files( "../models", ".md3" ).is_empty() );
+    assert!( vfs.list_files( "/", ".md3" ).is_empty() );
+    match vfs.read( "nothere.cfg" ) {
+        Err( VfsError::NotFound( name ) ) => assert_eq!( name, "nothere.cfg" ),
+        _ => panic!( "expected NotFound" ),
+    }
+
+    fs::remove_dir_all( &root ).unwrap();
+}
+
+#[test]
+fn later_shader_script_overrides_earlier_one()
+{
+    let root = scratch( "shaders" );
+    write( &root.join( "scripts/a.shader" ), b"box\n{\n{\nmap a.tga\n}\n}\nonly_a\n{\n}\n" );
+    write( &root.join( "scripts/b.shader" ),
+           b"box\n{\n{\nmap b.tga\n}\n}\nbroken\n{\ncull sideways\n}\nbox\n{\n{\nmap b2.tga\n}\n}\n" );
+
+    let mut vfs = Vfs::new();
+    vfs.add_game_dir( path_string( &root ) ).unwrap();
+    let set = ShaderSet::load_from( &mut vfs ).unwrap();
+    // b sorts after a and so wins; within b the first definition wins
+    assert_eq!( set.get( "box" ).unwrap().images(), vec!["b.tga"] );
+    assert!( set.get( "only_a" ).is_some() );
+    // Dropped with a warning; the engine would keep it with its default cull
+    assert!( set.get( "broken" ).is_none() );
+    assert_eq!( set.warnings.len(), 1 );
+    assert_eq!( set.warnings[0].script, "scripts/b.shader" );
+
+    fs::remove_dir_all( &root ).unwrap();
+}
@@ -0,0 +1,78 @@
+extern crate byteorder;
+extern crate md3_rs;
+
+mod common;
+
+use common::{build_md3_with_tags, two_surfaces, TagSpec};
+use md3_rs::md3::{Md3Error, Md3Model};
+use std::io::Cursor;
+
+fn fixture() -> Vec<u8>
+{
+    let tag = | name, z | TagSpec {
+        name, origin: [1.0, 2.0, z],
+        axis: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+    };
+    build_md3_with_tags( 2, &[
+        tag( "tag_head", 3.0 ), tag( "tag_weapon", 4.0 ),
+        tag( "tag_head", 5.0 ), tag( "tag_weapon", 6.0 ),
+    ], &two_surfaces( 2 ) )
+}
+
+fn write( m : &Md3Model ) -> Result<Vec<u8>, Md3Error>
+{
+    let mut out = Cursor::new( vec![] );
+    m.write_to( &mut out )?;
+    Ok( out.into_inner() )
+}
+
+#[test]
+fn write_to_reproduces_every_lump()
+{
+    let bytes = fixture();
+    let m = Md3Model::from_bytes( &bytes ).unwrap();
+    let written = write( &m ).unwrap();
+    // the fixture uses id's layout, so the output matches it byte for byte
+    assert_eq!( written, bytes );
+
+    let back = Md3Model::from_bytes( &written ).unwrap();
+    assert_eq!( back.header.tags_count, 2 );
+    assert_eq!( back.tag( 1, "tag_weapon" ).unwrap().origin.z, 6.0 );
+    assert_eq!( back.surfaces[1].data.xyz_normals[7].xyz, [160, 32, 32] );
+    assert_eq!( back.surfaces[1].data.triangles[1].indices, [2, 1, 3] );
+}
+
+#[test]
+fn write_to_refuses_tags_not_grouped_by_frame()
+{
+    let mut m = Md3Model::from_bytes( &fixture() ).unwrap();
+    m.tags.pop();
+    match write( &m ) {
+        Err( Md3Error::CountMismatch { structure: "Md3Tag", expected: 2, found: 3, .. } ) => {},
+        r => panic!( "expected a tag count mismatch, got {:?}", r.err() ),
+    }
+
+    m.frames.clear();
+    match write( &m ) {
+        Err( Md3Error::CountMismatch { structure: "Md3Tag", expected: 0, found: 3, .. } ) => {},
+        r => panic!( "expected a tag count mismatch, got {:?}", r.err() ),
+    }
+}
+
+#[test]
+fn write_to_refuses_surfaces_out_of_step_with_frames()
+{
+    let mut m = Md3Model::from_bytes( &fixture() ).unwrap();
+    m.surfaces[0].header.frame_count = 1;
+    match write( &m ) {
+        Err( Md3Error::CountMismatch { structure: "Md3Surface", expected: 2, found: 1, .. } ) => {},
+        r => panic!( "expected a surface frame count mismatch, got {:?}", r.err() ),
+    }
+
+    let mut m = Md3Model::from_bytes( &fixture() ).unwrap();
+    m.surfaces[1].data.xyz_normals.pop();
+    match write( &m ) {
+        Err( Md3Error::CountMismatch { structure: "Md3XyzNormal", expected: 8, found: 7, .. } ) => {},
+        r => panic!( "expected a vertex count mismatch, got {:?}", r.err() ),
+    }
+}